@@ -0,0 +1,39 @@
+use crate::{AppendOnlyBytes, BytesSlice};
+use alloc::vec::Vec;
+use bincode::{
+    de::Decoder,
+    enc::Encoder,
+    error::{DecodeError, EncodeError},
+    impl_borrow_decode, Decode, Encode,
+};
+
+impl Encode for BytesSlice {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.as_bytes().encode(encoder)
+    }
+}
+
+impl<Context> Decode<Context> for BytesSlice {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let bytes = Vec::<u8>::decode(decoder)?;
+        Ok(AppendOnlyBytes::from_vec(bytes).to_slice())
+    }
+}
+
+impl_borrow_decode!(BytesSlice);
+
+#[cfg(test)]
+mod test_bincode {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let config = bincode::config::standard();
+        let a = BytesSlice::from_bytes(&[1, 2, 3, 4]).slice_clone(1..);
+        let encoded = bincode::encode_to_vec(&a, config).unwrap();
+        assert_eq!(encoded, &[3, 2, 3, 4]);
+        let (b, len): (BytesSlice, usize) = bincode::decode_from_slice(&encoded, config).unwrap();
+        assert_eq!(len, encoded.len());
+        assert_eq!(a, b);
+    }
+}
@@ -0,0 +1,98 @@
+use crate::{AppendOnlyBytes, BytesSlice};
+use bytes::{buf::UninitSlice, Buf, BufMut};
+
+impl Buf for BytesSlice {
+    #[inline(always)]
+    fn remaining(&self) -> usize {
+        self.len()
+    }
+
+    #[inline(always)]
+    fn chunk(&self) -> &[u8] {
+        self.as_bytes()
+    }
+
+    #[inline]
+    fn advance(&mut self, cnt: usize) {
+        assert!(
+            cnt <= self.len(),
+            "cannot advance past `remaining`: {:?} <= {:?}",
+            cnt,
+            self.len()
+        );
+        self.slice_(cnt..);
+    }
+}
+
+/// A [`BufMut`] that appends to an [`AppendOnlyBytes`].
+pub struct AppendWriter<'a>(pub &'a mut AppendOnlyBytes);
+
+// SAFETY: `chunk_mut` only exposes the spare capacity after `len`, which is never referred to by
+// any `BytesSlice`, and `advance_mut` never moves `len` beyond the capacity.
+unsafe impl BufMut for AppendWriter<'_> {
+    #[inline(always)]
+    fn remaining_mut(&self) -> usize {
+        self.0.max_capacity().min(isize::MAX as usize) - self.0.len()
+    }
+
+    #[inline]
+    unsafe fn advance_mut(&mut self, cnt: usize) {
+        let new_len = self.0.len() + cnt;
+        assert!(
+            new_len <= self.0.capacity(),
+            "new_len = {}; capacity = {}",
+            new_len,
+            self.0.capacity()
+        );
+        // SAFETY: the caller must ensure the next `cnt` bytes are initialized
+        unsafe { self.0.set_len(new_len) };
+    }
+
+    #[inline]
+    fn chunk_mut(&mut self) -> &mut UninitSlice {
+        if self.0.capacity() == self.0.len() {
+            self.0.reserve(1);
+        }
+
+        let len = self.0.len();
+        let cap = self.0.capacity();
+        // SAFETY: the range `len..cap` is inside the allocation, and no one else can access it
+        unsafe { UninitSlice::from_raw_parts_mut(self.0.raw.ptr().add(len), cap - len) }
+    }
+}
+
+#[cfg(test)]
+mod test_bytes {
+    use super::*;
+
+    #[test]
+    fn buf() {
+        let mut a = BytesSlice::from_bytes(&[1, 2, 3, 4, 5]);
+        assert_eq!(a.remaining(), 5);
+        assert_eq!(a.get_u8(), 1);
+        assert_eq!(a.remaining(), 4);
+        let b = a.copy_to_bytes(2);
+        assert_eq!(&b[..], &[2, 3]);
+        assert_eq!(a.chunk(), &[4, 5]);
+        assert_eq!(a.get_u16(), 0x0405);
+        assert!(!a.has_remaining());
+
+        // not hidden by the inherent methods
+        let mut a = BytesSlice::from_bytes(&[1, 2, 3]);
+        let mut dst = [0; 2];
+        a.copy_to_slice(&mut dst);
+        assert_eq!(dst, [1, 2]);
+        assert_eq!(a.as_bytes(), &[3]);
+    }
+
+    #[test]
+    fn buf_mut() {
+        let mut a = AppendOnlyBytes::new();
+        a.push(1);
+        let mut w = AppendWriter(&mut a);
+        w.put_u32(0x02030405);
+        w.put_slice(&[6; 100]);
+        assert_eq!(&a[..5], &[1, 2, 3, 4, 5]);
+        assert_eq!(&a[5..], &[6; 100]);
+    }
+}
@@ -0,0 +1,60 @@
+use crc32fast::Hasher;
+
+use crate::{AppendOnlyBytes, BytesSlice};
+
+impl AppendOnlyBytes {
+    /// The CRC-32 checksum of the bytes.
+    #[inline]
+    pub fn crc32(&self) -> u32 {
+        crc32fast::hash(self.as_bytes())
+    }
+
+    /// Continue the checksum `prev`, computed over the first `prev_len` bytes, with the bytes
+    /// appended after them.
+    ///
+    /// The existing bytes never change, so it's the same as [`AppendOnlyBytes::crc32`] but only
+    /// reads the new bytes.
+    ///
+    /// # Panics
+    ///
+    /// If `prev_len > len`.
+    #[inline]
+    pub fn crc32_resume(&self, prev: u32, prev_len: usize) -> u32 {
+        let mut hasher = Hasher::new_with_initial_len(prev, prev_len as u64);
+        hasher.update(&self.as_bytes()[prev_len..]);
+        hasher.finalize()
+    }
+}
+
+impl BytesSlice {
+    /// The CRC-32 checksum of the bytes.
+    #[inline]
+    pub fn crc32(&self) -> u32 {
+        crc32fast::hash(self.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod test_crc {
+    use super::*;
+
+    #[test]
+    fn crc32() {
+        let mut a = AppendOnlyBytes::new();
+        assert_eq!(a.crc32(), 0);
+        a.push_slice(b"123456789");
+        // The check value of CRC-32/ISO-HDLC
+        assert_eq!(a.crc32(), 0xcbf43926);
+        assert_eq!(a.slice(..).crc32(), 0xcbf43926);
+
+        let mut crc = 0;
+        let mut len = 0;
+        for chunk in [b"hello".as_slice(), b"", b" world", b"!"] {
+            a.push_slice(chunk);
+            crc = a.crc32_resume(crc, len);
+            len = a.len();
+            assert_eq!(crc, a.crc32());
+        }
+        assert_eq!(a.slice(9..).crc32(), crc32fast::hash(b"hello world!"));
+    }
+}
@@ -0,0 +1,59 @@
+use std::io;
+
+use flate2::read::MultiGzDecoder;
+
+use crate::AppendOnlyBytes;
+
+/// The number of bytes to decompress into the spare capacity at a time.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+impl AppendOnlyBytes {
+    /// Decompress the gzip data `compressed` and append the result.
+    ///
+    /// All the members of concatenated gzip data are decompressed, e.g. the output of
+    /// `cat a.gz b.gz` or `pigz`.
+    ///
+    /// On error, the bytes decompressed before the error are still appended.
+    pub fn push_gzip_decompressed(&mut self, compressed: &[u8]) -> io::Result<()> {
+        let mut decoder = MultiGzDecoder::new(compressed);
+        while self.append_from_reader(&mut decoder, CHUNK_SIZE)? != 0 {}
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_flate2 {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::{io::Write, vec::Vec};
+
+    #[test]
+    fn round_trip() {
+        let data: Vec<u8> = (0..100_000u32).map(|x| (x % 251) as u8).collect();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&data).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(b"header");
+        a.push_gzip_decompressed(&compressed).unwrap();
+        assert_eq!(&a[..6], b"header");
+        assert_eq!(&a[6..], &data[..]);
+
+        assert!(a.push_gzip_decompressed(b"not gzip").is_err());
+    }
+
+    #[test]
+    fn multiple_members() {
+        let mut compressed = Vec::new();
+        for part in [&b"hello "[..], b"world"] {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(part).unwrap();
+            compressed.extend(encoder.finish().unwrap());
+        }
+
+        let mut a = AppendOnlyBytes::new();
+        a.push_gzip_decompressed(&compressed).unwrap();
+        assert_eq!(a.as_bytes(), b"hello world");
+    }
+}
@@ -0,0 +1,173 @@
+//! Iterators over [`BytesSlice`] and [`AppendOnlyBytes`](crate::AppendOnlyBytes).
+
+use core::{marker::PhantomData, str::Utf8Error};
+
+use crate::{find_byte, BytesSlice};
+
+/// Created by [`BytesSlice::split_shared`].
+pub struct Split<'a> {
+    rest: Option<BytesSlice>,
+    delim: u8,
+    _marker: PhantomData<&'a BytesSlice>,
+}
+
+impl<'a> Split<'a> {
+    #[inline]
+    pub(crate) fn new(slice: &'a BytesSlice, delim: u8) -> Self {
+        Self {
+            rest: Some(slice.clone()),
+            delim,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl Iterator for Split<'_> {
+    type Item = BytesSlice;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.rest.as_mut()?;
+        match rest.find_byte(self.delim) {
+            Some(index) => {
+                let item = rest.slice_clone(..index);
+                rest.slice_(index + 1..);
+                Some(item)
+            }
+            None => self.rest.take(),
+        }
+    }
+}
+
+/// Created by [`AppendOnlyBytes::lines`](crate::AppendOnlyBytes::lines).
+pub struct Lines<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Lines<'a> {
+    #[inline]
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { rest: bytes }
+    }
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = Result<&'a str, Utf8Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() {
+            return None;
+        }
+
+        let line = match find_byte(self.rest, b'\n') {
+            Some(index) => {
+                let mut line = &self.rest[..index];
+                self.rest = &self.rest[index + 1..];
+                if let [rest @ .., b'\r'] = line {
+                    line = rest;
+                }
+                line
+            }
+            None => core::mem::take(&mut self.rest),
+        };
+        Some(core::str::from_utf8(line))
+    }
+}
+
+/// Created by [`BytesSlice::chunks_shared`].
+pub struct Chunks<'a> {
+    rest: BytesSlice,
+    size: usize,
+    _marker: PhantomData<&'a BytesSlice>,
+}
+
+impl<'a> Chunks<'a> {
+    #[inline]
+    pub(crate) fn new(slice: &'a BytesSlice, size: usize) -> Self {
+        assert!(size != 0, "chunk size must be non-zero");
+        Self {
+            rest: slice.clone(),
+            size,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl Iterator for Chunks<'_> {
+    type Item = BytesSlice;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() {
+            return None;
+        }
+
+        let size = self.size.min(self.rest.len());
+        let item = self.rest.slice_clone(..size);
+        self.rest.slice_(size..);
+        Some(item)
+    }
+}
+
+/// Created by [`BytesSlice::windows_shared`].
+pub struct Windows<'a> {
+    slice: &'a BytesSlice,
+    size: usize,
+    offset: usize,
+}
+
+impl<'a> Windows<'a> {
+    #[inline]
+    pub(crate) fn new(slice: &'a BytesSlice, size: usize) -> Self {
+        assert!(size != 0, "window size must be non-zero");
+        Self {
+            slice,
+            size,
+            offset: 0,
+        }
+    }
+}
+
+impl Iterator for Windows<'_> {
+    type Item = BytesSlice;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset + self.size > self.slice.len() {
+            return None;
+        }
+
+        let item = self.slice.slice_clone(self.offset..self.offset + self.size);
+        self.offset += 1;
+        Some(item)
+    }
+}
+
+/// Created by [`BytesSlice::runs`].
+pub struct Runs<'a> {
+    rest: BytesSlice,
+    _marker: PhantomData<&'a BytesSlice>,
+}
+
+impl<'a> Runs<'a> {
+    #[inline]
+    pub(crate) fn new(slice: &'a BytesSlice) -> Self {
+        Self {
+            rest: slice.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl Iterator for Runs<'_> {
+    type Item = BytesSlice;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = self.rest.first_byte()?;
+        let len = self
+            .rest
+            .iter()
+            .position(|&b| b != first)
+            .unwrap_or(self.rest.len());
+        let item = self.rest.slice_clone(..len);
+        self.rest.slice_(len..);
+        Some(item)
+    }
+}
@@ -1,63 +1,119 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
 #![deny(clippy::undocumented_unsafe_blocks)]
 #![doc = include_str!("../README.md")]
 
+extern crate alloc;
+#[cfg(all(test, not(feature = "std")))]
+extern crate std;
+
+pub mod iter;
 mod raw_bytes;
-use std::{
-    fmt::Debug,
-    ops::{Deref, Index, RangeBounds},
+use alloc::{string::String, sync::Arc, vec::Vec};
+use core::{
+    borrow::Borrow,
+    fmt::{Debug, Display},
+    hash::Hash,
+    mem::MaybeUninit,
+    ops::{Deref, Index, Range, RangeBounds},
     slice::SliceIndex,
-    sync::Arc,
 };
 
-use raw_bytes::RawBytes;
+use iter::{Chunks, Lines, Runs, Split, Windows};
+use raw_bytes::{PackedArc, RawBytes};
+#[cfg(feature = "bincode")]
+mod bincode;
+#[cfg(feature = "bytes")]
+mod bytes;
+#[cfg(feature = "crc")]
+mod crc;
+#[cfg(feature = "flate2")]
+mod flate2;
+mod reader;
+#[cfg(feature = "rkyv")]
+mod rkyv;
 #[cfg(feature = "serde")]
-mod serde;
+pub mod serde;
+mod writer;
+#[cfg(feature = "bytes")]
+pub use self::bytes::AppendWriter;
+pub use reader::SliceReader;
+pub use writer::{RopeBuilder, SegmentedWriter};
 
+/// A growable byte buffer that can only be appended to.
+///
+/// The bytes a [`BytesSlice`] refers to are never moved or changed: when the buffer reallocates,
+/// the old allocation is kept alive by the slices referring to it.
 pub struct AppendOnlyBytes {
     raw: Arc<RawBytes>,
     len: usize,
+    /// Whether the buffer is not allowed to reallocate
+    fixed: bool,
+    policy: GrowthPolicy,
+    /// The capacity can never grow beyond it
+    max_capacity: usize,
 }
 
 impl Debug for AppendOnlyBytes {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("AppendOnlyBytes")
             .field("data", &self.as_bytes())
             .field("len", &self.len)
+            .field("fixed", &self.fixed)
             .finish()
     }
 }
 
 impl Clone for AppendOnlyBytes {
     fn clone(&self) -> Self {
-        let new = RawBytes::with_capacity(self.capacity());
+        // Only the fixed buffers need to keep the capacity, the others can grow on demand
+        let capacity = if self.fixed {
+            self.capacity()
+        } else {
+            self.len
+        };
+        let new = self.raw.with_capacity_like(capacity);
         // SAFETY: raw and new have at least self.len capacity
         unsafe {
-            std::ptr::copy_nonoverlapping(self.raw.ptr(), new.ptr(), self.len);
+            core::ptr::copy_nonoverlapping(self.raw.ptr(), new.ptr(), self.len);
         }
+        new.mark_initialized(self.len);
 
         Self {
             #[allow(clippy::arc_with_non_send_sync)]
             raw: Arc::new(new),
             len: self.len,
+            fixed: self.fixed,
+            policy: self.policy,
+            max_capacity: self.max_capacity,
         }
     }
 }
 
+impl PartialEq for AppendOnlyBytes {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl Eq for AppendOnlyBytes {}
+
+impl Hash for AppendOnlyBytes {
+    #[inline]
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.as_bytes().hash(state)
+    }
+}
+
 #[derive(Clone)]
 pub struct BytesSlice {
-    raw: Arc<RawBytes>,
-    #[cfg(not(feature = "u32_range"))]
-    start: usize,
-    #[cfg(not(feature = "u32_range"))]
-    end: usize,
-    #[cfg(feature = "u32_range")]
-    start: u32,
-    #[cfg(feature = "u32_range")]
-    end: u32,
+    raw: PackedArc,
+    start: Int,
+    end: Int,
 }
 
 impl Debug for BytesSlice {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("BytesSlice")
             .field("data", &&self[..])
             .field("start", &self.start)
@@ -66,6 +122,15 @@ impl Debug for BytesSlice {
     }
 }
 
+impl Display for BytesSlice {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match core::str::from_utf8(self.as_bytes()) {
+            Ok(s) => f.write_str(s),
+            Err(_) => f.write_str(&String::from_utf8_lossy(self.as_bytes())),
+        }
+    }
+}
+
 impl PartialEq for BytesSlice {
     fn eq(&self, other: &Self) -> bool {
         self.as_bytes() == other.as_bytes()
@@ -75,17 +140,99 @@ impl PartialEq for BytesSlice {
 impl Eq for BytesSlice {}
 
 impl PartialOrd for BytesSlice {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
 
 impl Ord for BytesSlice {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         self.as_bytes().cmp(other.as_bytes())
     }
 }
 
+macro_rules! impl_partial_eq {
+    ($lhs:ty, $rhs:ty) => {
+        impl PartialEq<$rhs> for $lhs {
+            #[inline]
+            fn eq(&self, other: &$rhs) -> bool {
+                AsRef::<[u8]>::as_ref(self) == AsRef::<[u8]>::as_ref(other)
+            }
+        }
+
+        impl PartialEq<$lhs> for $rhs {
+            #[inline]
+            fn eq(&self, other: &$lhs) -> bool {
+                AsRef::<[u8]>::as_ref(self) == AsRef::<[u8]>::as_ref(other)
+            }
+        }
+    };
+}
+
+impl_partial_eq!(AppendOnlyBytes, BytesSlice);
+impl_partial_eq!(BytesSlice, [u8]);
+impl_partial_eq!(BytesSlice, &[u8]);
+impl_partial_eq!(AppendOnlyBytes, [u8]);
+impl_partial_eq!(AppendOnlyBytes, &[u8]);
+impl_partial_eq!(BytesSlice, str);
+impl_partial_eq!(BytesSlice, &str);
+
+impl Hash for BytesSlice {
+    #[inline]
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.as_bytes().hash(state)
+    }
+}
+
+impl AsRef<[u8]> for BytesSlice {
+    #[inline(always)]
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl Borrow<[u8]> for BytesSlice {
+    #[inline(always)]
+    fn borrow(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl<'a> IntoIterator for &'a BytesSlice {
+    type Item = &'a u8;
+    type IntoIter = core::slice::Iter<'a, u8>;
+
+    #[inline(always)]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::io::Read for BytesSlice {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = buf.len().min(self.len());
+        buf[..n].copy_from_slice(&self.as_bytes()[..n]);
+        self.slice_(n..);
+        Ok(n)
+    }
+}
+
+/// The whole slice is already in memory, so `fill_buf` returns the remaining bytes without copying.
+#[cfg(feature = "std")]
+impl std::io::BufRead for BytesSlice {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        Ok(self.as_bytes())
+    }
+
+    #[inline]
+    fn consume(&mut self, amt: usize) {
+        self.slice_(amt.min(self.len())..);
+    }
+}
+
 // SAFETY: It's Send & Sync because it doesn't have interior mutability. And the owner of the type can only append data to it.
 // All the existing data will never be changed.
 unsafe impl Send for AppendOnlyBytes {}
@@ -94,6 +241,43 @@ unsafe impl Send for AppendOnlyBytes {}
 unsafe impl Sync for AppendOnlyBytes {}
 
 const MIN_CAPACITY: usize = 32;
+/// [`AppendOnlyBytes::with_capacity`] rounds the capacity up to a multiple of it.
+const CAPACITY_ALIGN: usize = 16;
+/// Under `u32_range`, the buffer cannot grow beyond what the offsets of `BytesSlice` can
+/// address. `u16_range` doesn't limit it, since 64KB is too small for every buffer in the build.
+#[cfg(feature = "u32_range")]
+const DEFAULT_MAX_CAPACITY: usize = u32::MAX as usize;
+#[cfg(not(feature = "u32_range"))]
+const DEFAULT_MAX_CAPACITY: usize = usize::MAX;
+
+/// How [`AppendOnlyBytes::reserve`] grows the capacity when it runs out of space.
+///
+/// The new capacity is the old one multiplied by `growth_factor`, but at least `min_capacity`
+/// and the requested size. The default is doubling with a minimum of 32 bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GrowthPolicy {
+    pub min_capacity: usize,
+    /// It must be greater than 1.
+    pub growth_factor: f64,
+}
+
+impl Default for GrowthPolicy {
+    fn default() -> Self {
+        Self {
+            min_capacity: MIN_CAPACITY,
+            growth_factor: 2.0,
+        }
+    }
+}
+
+impl GrowthPolicy {
+    #[inline]
+    fn grow(&self, capacity: usize) -> usize {
+        // The float-to-int cast saturates on overflow
+        let new_capacity = (capacity as f64 * self.growth_factor) as usize;
+        new_capacity.max(capacity.saturating_add(1))
+    }
+}
 impl AppendOnlyBytes {
     #[inline(always)]
     pub fn new() -> Self {
@@ -106,351 +290,3211 @@ impl AppendOnlyBytes {
         unsafe { self.raw.slice(..self.len) }
     }
 
+    /// The pointer to the first initialized byte.
+    ///
+    /// The `len()` bytes behind it stay valid and unchanged as long as `self` is alive and
+    /// not mutated. They must not be written through this pointer.
     #[inline(always)]
-    pub fn with_capacity(capacity: usize) -> Self {
-        #[allow(clippy::arc_with_non_send_sync)]
-        let raw = Arc::new(RawBytes::with_capacity(capacity));
-        Self { raw, len: 0 }
+    pub fn as_ptr(&self) -> *const u8 {
+        self.as_bytes().as_ptr()
     }
 
+    /// # Safety
+    ///
+    /// The bytes must be valid UTF-8.
     #[inline(always)]
-    pub fn len(&self) -> usize {
-        self.len
+    pub unsafe fn as_str_unchecked(&self) -> &str {
+        // SAFETY: Caller must ensure that the bytes are valid UTF-8
+        unsafe { core::str::from_utf8_unchecked(self.as_bytes()) }
     }
 
     #[inline(always)]
-    pub fn capacity(&self) -> usize {
-        self.raw.capacity()
+    pub fn iter(&self) -> core::slice::Iter<'_, u8> {
+        self.as_bytes().iter()
     }
 
-    #[must_use]
-    pub fn is_empty(&self) -> bool {
-        self.len() == 0
+    #[inline(always)]
+    pub fn first_byte(&self) -> Option<u8> {
+        self.as_bytes().first().copied()
     }
 
     #[inline(always)]
-    pub fn push_slice(&mut self, slice: &[u8]) {
-        self.reserve(slice.len());
-        // SAFETY: We have reserved enough space for the slice
-        unsafe {
-            std::ptr::copy_nonoverlapping(
-                slice.as_ptr(),
-                self.raw.ptr().add(self.len),
-                slice.len(),
-            );
-            self.len += slice.len();
-        }
+    pub fn last_byte(&self) -> Option<u8> {
+        self.as_bytes().last().copied()
     }
 
     #[inline(always)]
-    pub fn push_str(&mut self, slice: &str) {
-        self.push_slice(slice.as_bytes());
+    pub fn get_byte(&self, index: usize) -> Option<u8> {
+        self.as_bytes().get(index).copied()
     }
 
     #[inline(always)]
-    pub fn push(&mut self, byte: u8) {
-        self.reserve(1);
-        // SAFETY: We have reserved enough space for the byte
-        unsafe {
-            std::ptr::write(self.raw.ptr().add(self.len), byte);
-            self.len += 1;
-        }
+    pub fn starts_with(&self, prefix: &[u8]) -> bool {
+        self.as_bytes().starts_with(prefix)
     }
 
-    #[inline]
-    pub fn reserve(&mut self, size: usize) {
-        let target_capacity = self.len() + size;
-        if target_capacity > self.capacity() {
-            let mut new_capacity = (self.capacity() * 2).max(MIN_CAPACITY);
-            while new_capacity < target_capacity {
-                new_capacity *= 2;
-            }
-
-            let src = std::mem::replace(self, Self::with_capacity(new_capacity));
-            // SAFETY: copy from src to dst, both have at least the capacity of src.len()
-            unsafe {
-                std::ptr::copy_nonoverlapping(src.raw.ptr(), self.raw.ptr(), src.len());
-                self.len = src.len();
-            }
-        }
+    #[inline(always)]
+    pub fn ends_with(&self, suffix: &[u8]) -> bool {
+        self.as_bytes().ends_with(suffix)
     }
 
     #[inline]
-    pub fn slice_str(&self, range: impl RangeBounds<usize>) -> Result<&str, std::str::Utf8Error> {
-        let (start, end) = get_range(range, self.len());
-        // SAFETY: data inside start..end is initialized
-        std::str::from_utf8(unsafe { self.raw.slice(start..end) })
+    pub fn contains_byte(&self, needle: u8) -> bool {
+        find_byte(self.as_bytes(), needle).is_some()
     }
 
+    /// The number of occurrences of `needle`.
     #[inline]
-    pub fn slice(&self, range: impl RangeBounds<usize>) -> BytesSlice {
-        let (start, end) = get_range(range, self.len());
-        BytesSlice::new(self.raw.clone(), start, end)
+    pub fn count_byte(&self, needle: u8) -> usize {
+        count_byte(self.as_bytes(), needle)
     }
 
+    /// Create a buffer with at least `capacity` bytes of capacity.
+    ///
+    /// The capacity is rounded up to a multiple of 16, since allocators hand out memory in size
+    /// classes anyway. Use [`AppendOnlyBytes::with_exact_capacity`] for an exact capacity.
     #[inline(always)]
-    pub fn to_slice(self) -> BytesSlice {
-        let end = self.len();
-        BytesSlice::new(self.raw, 0, end)
+    pub fn with_capacity(capacity: usize) -> Self {
+        // Leave the overflow to the allocation, which panics with "capacity overflow"
+        let capacity = capacity
+            .checked_next_multiple_of(CAPACITY_ALIGN)
+            .unwrap_or(capacity);
+        Self::with_exact_capacity(capacity)
     }
-}
 
-impl Default for AppendOnlyBytes {
+    /// Create a buffer with exactly `capacity` bytes of capacity. Unlike
+    /// [`AppendOnlyBytes::fixed_with_capacity`], it can still grow.
     #[inline(always)]
-    fn default() -> Self {
-        Self::new()
+    pub fn with_exact_capacity(capacity: usize) -> Self {
+        #[allow(clippy::arc_with_non_send_sync)]
+        let raw = Arc::new(RawBytes::with_capacity(capacity));
+        Self {
+            raw,
+            len: 0,
+            fixed: false,
+            policy: GrowthPolicy::default(),
+            max_capacity: DEFAULT_MAX_CAPACITY,
+        }
     }
-}
-
-#[inline(always)]
-fn get_range(range: impl RangeBounds<usize>, max_len: usize) -> (usize, usize) {
-    let start = match range.start_bound() {
-        std::ops::Bound::Included(&v) => v,
-        std::ops::Bound::Excluded(&v) => v + 1,
-        std::ops::Bound::Unbounded => 0,
-    };
-    let end = match range.end_bound() {
-        std::ops::Bound::Included(&v) => v + 1,
-        std::ops::Bound::Excluded(&v) => v,
-        std::ops::Bound::Unbounded => max_len,
-    };
-    assert!(start <= end);
-    assert!(end <= max_len);
-    (start, end)
-}
-
-impl<I: SliceIndex<[u8]>> Index<I> for AppendOnlyBytes {
-    type Output = I::Output;
 
+    /// Create a buffer whose memory is aligned to `align`, e.g. a page or a cache line.
+    ///
+    /// The alignment is kept when the buffer reallocates or is cloned. Converting it
+    /// [`AppendOnlyBytes::into_vec`] copies the data, since `Vec<u8>` cannot own aligned memory.
+    ///
+    /// # Panics
+    ///
+    /// If `align` is not a power of two.
     #[inline]
-    fn index(&self, index: I) -> &Self::Output {
-        // SAFETY: data inside 0..self.len is initialized
-        unsafe { Index::index(self.raw.slice(..self.len), index) }
+    pub fn with_capacity_aligned(capacity: usize, align: usize) -> Self {
+        #[allow(clippy::arc_with_non_send_sync)]
+        let raw = Arc::new(RawBytes::with_capacity_aligned(capacity, align));
+        Self {
+            raw,
+            len: 0,
+            fixed: false,
+            policy: GrowthPolicy::default(),
+            max_capacity: DEFAULT_MAX_CAPACITY,
+        }
     }
-}
-
-// SAFETY: It's Send & Sync because it doesn't have interior mutability. All the accessible data in this type will never be changed.
-unsafe impl Send for BytesSlice {}
-// SAFETY: It's Send & Sync because it doesn't have interior mutability. All the accessible data in this type will never be changed.
-unsafe impl Sync for BytesSlice {}
 
-#[cfg(not(feature = "u32_range"))]
-type Int = usize;
-#[cfg(feature = "u32_range")]
-type Int = u32;
+    /// Create an empty buffer whose memory is allocated by `allocator`.
+    ///
+    /// The allocator is shared by all the allocations of the buffer, including the ones of
+    /// reallocation and `Clone`.
+    ///
+    /// The allocator must be `Send + Sync + 'static`, because the memory is freed by whichever
+    /// [`BytesSlice`] is dropped last, on any thread and possibly after the buffer is gone. So a
+    /// borrowed arena handle like `&'a Bump` is rejected; pass a `'static` handle instead, e.g.
+    /// an `Arc` of the arena or a leaked `&'static` reference to it.
+    ///
+    /// ```compile_fail
+    /// #![feature(allocator_api)]
+    /// use append_only_bytes::AppendOnlyBytes;
+    /// use std::alloc::Global;
+    ///
+    /// let arena = Global;
+    /// let _ = AppendOnlyBytes::new_in(&arena); // `arena` does not live long enough
+    /// ```
+    #[cfg(feature = "allocator_api")]
+    #[inline]
+    pub fn new_in<A>(allocator: A) -> Self
+    where
+        A: core::alloc::Allocator + Send + Sync + 'static,
+    {
+        Self::with_capacity_in(0, allocator)
+    }
 
-impl BytesSlice {
-    #[inline(always)]
-    fn new(raw: Arc<RawBytes>, start: usize, end: usize) -> Self {
+    /// Create a buffer with exactly `capacity` bytes of capacity allocated by `allocator`.
+    #[cfg(feature = "allocator_api")]
+    #[inline]
+    pub fn with_capacity_in<A>(capacity: usize, allocator: A) -> Self
+    where
+        A: core::alloc::Allocator + Send + Sync + 'static,
+    {
+        #[allow(clippy::arc_with_non_send_sync)]
+        let raw = Arc::new(RawBytes::with_capacity_in(capacity, 1, Arc::new(allocator)));
         Self {
             raw,
-            start: start as Int,
-            end: end as Int,
+            len: 0,
+            fixed: false,
+            policy: GrowthPolicy::default(),
+            max_capacity: DEFAULT_MAX_CAPACITY,
         }
     }
 
+    /// Create a buffer that never reallocates.
+    ///
+    /// All the slices created from it stay in the same allocation, so adjacent slices can always
+    /// be merged. Appending beyond the capacity panics, use [`AppendOnlyBytes::try_push_slice`]
+    /// to handle it gracefully.
     #[inline(always)]
-    pub fn empty() -> Self {
-        Self {
-            #[allow(clippy::arc_with_non_send_sync)]
-            raw: Arc::new(RawBytes::with_capacity(0)),
-            start: 0,
-            end: 0,
-        }
+    pub fn fixed_with_capacity(capacity: usize) -> Self {
+        let mut bytes = Self::with_exact_capacity(capacity);
+        bytes.fixed = true;
+        bytes.max_capacity = capacity;
+        bytes
     }
 
     #[inline(always)]
-    fn bytes(&self) -> &[u8] {
-        // SAFETY: data inside this range is guaranteed to be initialized
-        unsafe { self.raw.slice(self.start()..self.end()) }
+    pub fn is_fixed(&self) -> bool {
+        self.fixed
     }
 
+    /// Create a buffer whose capacity never grows beyond `max`, e.g. to bound the memory used
+    /// when decoding untrusted input.
+    ///
+    /// Growing beyond `max` panics, use [`AppendOnlyBytes::try_reserve`] and
+    /// [`AppendOnlyBytes::try_extend_from_slice`] to handle it gracefully.
+    #[inline]
+    #[allow(clippy::unnecessary_min_or_max)]
+    pub fn with_max_capacity(max: usize) -> Self {
+        let mut bytes = Self::new();
+        bytes.max_capacity = max.min(DEFAULT_MAX_CAPACITY);
+        bytes
+    }
+
+    /// The limit of the capacity. Without a limit, it's `usize::MAX`, or `u32::MAX` under the
+    /// `u32_range` feature.
     #[inline(always)]
-    pub fn as_bytes(&self) -> &[u8] {
-        // SAFETY: data inside this range is guaranteed to be initialized
-        unsafe { self.raw.slice(self.start()..self.end()) }
+    pub fn max_capacity(&self) -> usize {
+        self.max_capacity
+    }
+
+    /// Set how the capacity grows on the following reallocations.
+    ///
+    /// # Panics
+    ///
+    /// If `policy.growth_factor` is not greater than 1.
+    #[inline]
+    pub fn set_growth_policy(&mut self, policy: GrowthPolicy) {
+        assert!(
+            policy.growth_factor > 1.0,
+            "growth factor must be greater than 1: {}",
+            policy.growth_factor
+        );
+        self.policy = policy;
     }
 
     #[inline(always)]
-    #[allow(clippy::unnecessary_cast)]
-    pub fn len(&self) -> usize {
-        (self.end - self.start) as usize
+    pub fn growth_policy(&self) -> GrowthPolicy {
+        self.policy
     }
 
-    #[allow(clippy::arc_with_non_send_sync)]
-    pub fn from_bytes(bytes: &[u8]) -> Self {
-        let new = RawBytes::with_capacity(bytes.len());
-        // SAFETY: raw and new have at least self.len capacity
-        unsafe {
-            std::ptr::copy_nonoverlapping(bytes.as_ptr(), new.ptr(), bytes.len());
+    /// Adopt the memory of `vec` without copying.
+    #[inline(always)]
+    pub fn from_vec(vec: Vec<u8>) -> Self {
+        let len = vec.len();
+        // `RawBytes` keeps the allocation of `vec` as is, so the first `len` bytes stay initialized
+        #[allow(clippy::arc_with_non_send_sync)]
+        let raw = Arc::new(RawBytes::from(vec));
+        Self {
+            raw,
+            len,
+            fixed: false,
+            policy: GrowthPolicy::default(),
+            max_capacity: DEFAULT_MAX_CAPACITY,
+        }
+    }
+
+    /// Concatenate `slices` into a new buffer with a single allocation.
+    pub fn from_slices(slices: &[&[u8]]) -> Self {
+        let mut bytes = Self::with_exact_capacity(slices.iter().map(|x| x.len()).sum());
+        for slice in slices {
+            bytes.push_slice(slice);
         }
+        bytes
+    }
 
-        Self {
-            raw: Arc::new(new),
-            start: 0,
-            end: bytes.len() as Int,
+    /// Concatenate `slices` into a new buffer with a single allocation.
+    pub fn concat_slices(slices: &[BytesSlice]) -> Self {
+        let mut bytes = Self::with_exact_capacity(slices.iter().map(|x| x.len()).sum());
+        for slice in slices {
+            bytes.push_slice(slice);
         }
+        bytes
     }
 
-    #[inline(always)]
-    pub fn is_empty(&self) -> bool {
-        self.end == self.start
+    /// Create a buffer of `count` copies of `byte`.
+    #[inline]
+    pub fn from_repeated(byte: u8, count: usize) -> Self {
+        let mut bytes = Self::with_exact_capacity(count);
+        bytes.push_repeated(byte, count);
+        bytes
     }
 
     #[inline(always)]
-    #[allow(clippy::unnecessary_cast)]
-    pub fn slice_clone(&self, range: impl std::ops::RangeBounds<usize>) -> Self {
-        let (start, end) = get_range(range, (self.end - self.start) as usize);
-        Self::new(self.raw.clone(), self.start() + start, self.start() + end)
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
     }
 
     #[inline(always)]
-    #[allow(clippy::unnecessary_cast)]
-    pub fn slice_(&mut self, range: impl std::ops::RangeBounds<usize>) {
-        let (start, end) = get_range(range, (self.end - self.start) as usize);
-        self.end = self.start + end as Int;
-        self.start += start as Int;
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.raw.capacity()
     }
 
     #[inline(always)]
-    pub fn ptr_eq(&self, other: &Self) -> bool {
-        Arc::ptr_eq(&self.raw, &other.raw)
+    pub fn remaining_capacity(&self) -> usize {
+        self.capacity() - self.len()
     }
 
+    /// Whether appending `additional` bytes will grow the buffer.
+    ///
+    /// Growing may move the bytes even if no `BytesSlice` refers to the buffer, so the pointers
+    /// from [`AppendOnlyBytes::as_ptr`] are invalidated. The slices created before the growth
+    /// cannot be merged with the ones created after it.
     #[inline(always)]
-    pub fn can_merge(&self, other: &Self) -> bool {
-        self.ptr_eq(other) && self.end == other.start
+    pub fn will_realloc(&self, additional: usize) -> bool {
+        additional > self.remaining_capacity()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 
     #[inline(always)]
-    pub fn try_merge(&mut self, other: &Self) -> Result<(), MergeFailed> {
-        if self.can_merge(other) {
-            self.end = other.end;
-            Ok(())
-        } else {
-            Err(MergeFailed)
+    pub fn push_slice(&mut self, slice: &[u8]) {
+        self.reserve(slice.len());
+        // SAFETY: We have reserved enough space for the slice
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                slice.as_ptr(),
+                self.raw.ptr().add(self.len),
+                slice.len(),
+            );
+            self.set_len(self.len + slice.len());
         }
     }
 
+    /// Push a large `slice` with [`AppendOnlyBytes::reserve_exact`], so the capacity is not
+    /// rounded up by the growth policy when it reallocates.
     #[inline]
-    pub fn slice_str(&self, range: impl RangeBounds<usize>) -> Result<&str, std::str::Utf8Error> {
-        let (start, end) = get_range(range, self.len());
-        std::str::from_utf8(&self.deref()[start..end])
+    pub fn push_large_slice(&mut self, slice: &[u8]) {
+        self.reserve_exact(slice.len());
+        self.push_slice(slice);
     }
 
-    #[inline(always)]
-    #[allow(clippy::unnecessary_cast)]
-    pub fn start(&self) -> usize {
-        self.start as usize
+    /// Push `slice` and return the slice of the newly written bytes.
+    #[inline]
+    pub fn push_slice_ret(&mut self, slice: &[u8]) -> BytesSlice {
+        let start = self.len();
+        self.push_slice(slice);
+        self.slice(start..)
     }
 
-    #[inline(always)]
-    #[allow(clippy::unnecessary_cast)]
-    pub fn end(&self) -> usize {
-        self.end as usize
+    /// Push `slice` and return whether it reallocated, i.e. the bytes moved to another address.
+    ///
+    /// After reallocation, the existing slices cannot be merged with the new ones, and the
+    /// pointers from [`AppendOnlyBytes::as_ptr`] are invalidated. When no slice refers to the
+    /// buffer, it may grow in place and keep the address, which is not a reallocation.
+    #[inline]
+    pub fn push_slice_tracked(&mut self, slice: &[u8]) -> bool {
+        let old = Arc::as_ptr(&self.raw);
+        let old_ptr = self.raw.ptr();
+        self.push_slice(slice);
+        !core::ptr::eq(old, Arc::as_ptr(&self.raw)) || old_ptr != self.raw.ptr()
     }
-}
 
-#[derive(Debug)]
-pub struct MergeFailed;
+    /// Push the content of `slice`, which may come from this buffer.
+    ///
+    /// The bytes are always copied, there is no fast path for a `slice` that ends at `len` (see
+    /// [`AppendOnlyBytes::is_contiguous_with`]). Extending `len` over it would not repeat its
+    /// content: the new bytes would be whatever lies after `len`, which is uninitialized or
+    /// stale. Use [`BytesSlice::try_merge`] to grow a slice over bytes pushed after it instead.
+    #[inline]
+    pub fn push_bytes_slice(&mut self, slice: &BytesSlice) {
+        // `slice` keeps the old allocation alive if `reserve` reallocates, so the source
+        // stays valid and never overlaps with the destination after `len`
+        self.push_slice(slice.as_bytes());
+    }
 
-impl Deref for BytesSlice {
-    type Target = [u8];
+    /// Move the bytes of `other` to the end of this buffer, like [`Vec::append`].
+    ///
+    /// The bytes are copied, since the two buffers don't share the allocation. `other` is cleared
+    /// afterwards if no `BytesSlice` refers to it, otherwise it's left unchanged.
+    #[inline]
+    pub fn append(&mut self, other: &mut AppendOnlyBytes) {
+        self.push_slice(other.as_bytes());
+        let _ = other.clear();
+    }
 
-    #[inline(always)]
-    fn deref(&self) -> &Self::Target {
-        self.bytes()
+    /// Push `value` in unsigned LEB128 encoding.
+    #[inline]
+    pub fn push_uvarint(&mut self, mut value: u64) {
+        let mut buf = [0; 10];
+        let mut len = 0;
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                buf[len] = byte;
+                len += 1;
+                break;
+            }
+
+            buf[len] = byte | 0x80;
+            len += 1;
+        }
+        self.push_slice(&buf[..len]);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::{
-        sync::mpsc::{self, Receiver, Sender},
-        thread,
+    /// Push `value` in zigzag + unsigned LEB128 encoding.
+    #[inline]
+    pub fn push_ivarint(&mut self, value: i64) {
+        self.push_uvarint(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    /// Push `slice` only if it fits in the remaining capacity, so it never reallocates.
+    #[inline]
+    pub fn try_push_slice(&mut self, slice: &[u8]) -> Result<(), CapacityExceeded> {
+        if self.will_realloc(slice.len()) {
+            return Err(CapacityExceeded);
+        }
+
+        self.push_slice(slice);
+        Ok(())
+    }
+
+    /// Same as [`AppendOnlyBytes::push_slice`]. Bytes are always appended at the end.
+    #[inline(always)]
+    pub fn extend_from_slice(&mut self, other: &[u8]) {
+        self.push_slice(other);
+    }
+
+    #[inline(always)]
+    pub fn push_str(&mut self, slice: &str) {
+        self.push_slice(slice.as_bytes());
+    }
+
+    #[inline(always)]
+    pub fn push(&mut self, byte: u8) {
+        self.reserve(1);
+        // SAFETY: We have reserved enough space for the byte
+        unsafe {
+            core::ptr::write(self.raw.ptr().add(self.len), byte);
+            self.set_len(self.len + 1);
+        }
+    }
+
+    /// Push `count` copies of `byte`.
+    #[inline]
+    pub fn push_repeated(&mut self, byte: u8, count: usize) {
+        self.reserve(count);
+        // SAFETY: We have reserved enough space for `count` bytes
+        unsafe {
+            core::ptr::write_bytes(self.raw.ptr().add(self.len), byte, count);
+            self.set_len(self.len + count);
+        }
+    }
+
+    /// Push `byte` only if there is spare capacity, otherwise hand it back.
+    #[inline]
+    pub fn push_within_capacity(&mut self, byte: u8) -> Result<(), u8> {
+        if self.remaining_capacity() == 0 {
+            return Err(byte);
+        }
+
+        self.push(byte);
+        Ok(())
+    }
+
+    #[inline]
+    pub fn reserve(&mut self, size: usize) {
+        let target_capacity = self.len().checked_add(size).expect("capacity overflow");
+        if target_capacity > self.capacity() {
+            self.assert_growable(target_capacity);
+            // Growing by the policy once is enough to amortize the appends. If it's still not
+            // enough, the requested size is allocated directly.
+            let new_capacity = self
+                .policy
+                .grow(self.capacity())
+                .max(self.policy.min_capacity)
+                .max(target_capacity)
+                .min(self.max_capacity);
+
+            self.realloc(new_capacity);
+        }
+    }
+
+    /// Like [`AppendOnlyBytes::reserve`], but returns an error instead of panicking if the
+    /// capacity would exceed [`AppendOnlyBytes::max_capacity`]. Nothing is allocated then.
+    #[inline]
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), CapacityLimitExceeded> {
+        match self.len().checked_add(additional) {
+            Some(target_capacity) if target_capacity <= self.max_capacity => {
+                self.reserve(additional);
+                Ok(())
+            }
+            _ => Err(CapacityLimitExceeded),
+        }
+    }
+
+    /// Push `slice` if it doesn't make the capacity exceed [`AppendOnlyBytes::max_capacity`].
+    #[inline]
+    pub fn try_extend_from_slice(&mut self, slice: &[u8]) -> Result<(), CapacityLimitExceeded> {
+        self.try_reserve(slice.len())?;
+        self.push_slice(slice);
+        Ok(())
+    }
+
+    /// Reserve exactly `additional` more bytes, without rounding the capacity up.
+    #[inline]
+    pub fn reserve_exact(&mut self, additional: usize) {
+        let target_capacity = self
+            .len()
+            .checked_add(additional)
+            .expect("capacity overflow");
+        if target_capacity > self.capacity() {
+            self.assert_growable(target_capacity);
+            self.realloc(target_capacity);
+        }
+    }
+
+    /// Shrink the capacity to `len`.
+    ///
+    /// It does nothing if there are `BytesSlice`s referring to the buffer, because they
+    /// still depend on the current allocation. It also does nothing in fixed mode.
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        if !self.fixed && self.capacity() > self.len && self.is_unique() {
+            self.realloc(self.len);
+        }
+    }
+
+    #[inline]
+    fn assert_growable(&self, target_capacity: usize) {
+        assert!(
+            !self.fixed,
+            "fixed capacity exceeded: {} > {}",
+            target_capacity,
+            self.capacity()
+        );
+        assert!(
+            target_capacity <= self.max_capacity,
+            "capacity limit exceeded: {} > {}",
+            target_capacity,
+            self.max_capacity
+        );
+    }
+
+    #[inline]
+    fn realloc(&mut self, new_capacity: usize) {
+        debug_assert!(!self.fixed);
+        debug_assert!(new_capacity >= self.len());
+        if new_capacity > self.capacity() {
+            if let Some(raw) = Arc::get_mut(&mut self.raw) {
+                // SAFETY: data inside `..len` is initialized, and no `BytesSlice` refers to it
+                if unsafe { raw.grow_in_place(self.len, new_capacity) } {
+                    return;
+                }
+            }
+        }
+
+        let new = self.raw.with_capacity_like(new_capacity);
+        // SAFETY: copy from raw to new, both have at least the capacity of self.len
+        unsafe {
+            core::ptr::copy_nonoverlapping(self.raw.ptr(), new.ptr(), self.len);
+        }
+        new.mark_initialized(self.len);
+        #[allow(clippy::arc_with_non_send_sync)]
+        {
+            self.raw = Arc::new(new);
+        }
+    }
+
+    /// Shorten the buffer to `new_len`, so that the truncated region can be overwritten by
+    /// future appends. It does nothing if `new_len >= len`.
+    ///
+    /// Truncation is only allowed when there is no `BytesSlice` referring to the buffer
+    /// (i.e. `Arc::strong_count == 1`), otherwise the bytes they refer to may be changed.
+    #[inline]
+    pub fn truncate(&mut self, new_len: usize) -> Result<(), StillShared> {
+        if new_len >= self.len {
+            return Ok(());
+        }
+
+        if !self.is_unique() {
+            return Err(StillShared);
+        }
+
+        self.len = new_len;
+        Ok(())
+    }
+
+    /// The uninitialized region after `len`.
+    ///
+    /// No `BytesSlice` can refer to this region, so it can be written even if the buffer is
+    /// shared. Use [`AppendOnlyBytes::set_len`] to commit the written bytes.
+    #[inline]
+    pub fn spare_capacity_mut(&mut self) -> &mut [MaybeUninit<u8>] {
+        // SAFETY: `len..capacity` is inside the allocation and is not referred to by anyone else.
+        // `&mut self` guarantees that no other reference to this region is alive.
+        unsafe {
+            core::slice::from_raw_parts_mut(
+                self.raw.ptr().add(self.len) as *mut MaybeUninit<u8>,
+                self.capacity() - self.len,
+            )
+        }
+    }
+
+    /// Fill the spare capacity after `len` with zeroes, so the previous content of the memory
+    /// cannot be leaked through it.
+    ///
+    /// No `BytesSlice` can refer to the spare capacity, so it's fine to call it on a shared buffer.
+    #[inline]
+    pub fn zero_spare_capacity(&mut self) {
+        for x in self.spare_capacity_mut() {
+            x.write(0);
+        }
+        self.raw.mark_initialized(self.capacity());
+    }
+
+    /// # Safety
+    ///
+    /// - `new_len` must be less than or equal to `capacity()`
+    /// - The data inside `..new_len` must be initialized
+    /// - If `new_len < len`, there must be no `BytesSlice` referring to this buffer
+    #[inline]
+    pub unsafe fn set_len(&mut self, new_len: usize) {
+        debug_assert!(new_len <= self.capacity());
+        debug_assert!(new_len >= self.len || self.is_unique());
+        self.raw.mark_initialized(new_len);
+        self.len = new_len;
+    }
+
+    /// Read at most `max` bytes from `reader` directly into the buffer.
+    ///
+    /// Returns the number of bytes read.
+    #[cfg(feature = "std")]
+    pub fn append_from_reader<R: std::io::Read>(
+        &mut self,
+        reader: &mut R,
+        max: usize,
+    ) -> std::io::Result<usize> {
+        let mut result = Ok(0);
+        self.append_with(max, |buf| {
+            result = reader.read(buf);
+            *result.as_ref().unwrap_or(&0)
+        });
+        result
+    }
+
+    /// Reserve `max` bytes and let `f` write to the zero-filled region after `len`, then commit
+    /// the first `n` bytes of it, where `n` is returned by `f`.
+    ///
+    /// Use [`AppendOnlyBytes::append_with_unchecked`] to skip the zero-filling.
+    ///
+    /// # Panics
+    ///
+    /// If `f` returns a number greater than `max`.
+    #[inline]
+    pub fn append_with<F>(&mut self, max: usize, f: F)
+    where
+        F: FnOnce(&mut [u8]) -> usize,
+    {
+        let write = |spare: &mut [MaybeUninit<u8>]| {
+            spare.fill(MaybeUninit::new(0));
+            // SAFETY: all the bytes in `spare` are initialized above
+            f(unsafe { &mut *(spare as *mut [MaybeUninit<u8>] as *mut [u8]) })
+        };
+        // SAFETY: `write` initializes all the bytes of the given slice
+        unsafe { self.append_with_unchecked(max, write) };
+    }
+
+    /// Reserve `max` bytes and let `f` write to the uninitialized region after `len`, then
+    /// commit the number of bytes returned by `f`.
+    ///
+    /// # Safety
+    ///
+    /// `f` must initialize the first `n` bytes of the given slice, where `n` is the returned value.
+    ///
+    /// # Panics
+    ///
+    /// If `f` returns a number greater than `max`.
+    #[inline]
+    pub unsafe fn append_with_unchecked<F>(&mut self, max: usize, f: F)
+    where
+        F: FnOnce(&mut [MaybeUninit<u8>]) -> usize,
+    {
+        self.reserve(max);
+        let n = f(&mut self.spare_capacity_mut()[..max]);
+        assert!(n <= max, "initialized more than reserved: {} > {}", n, max);
+        // SAFETY: Caller must ensure that the first `n` bytes of the spare capacity are initialized
+        unsafe { self.set_len(self.len + n) };
+    }
+
+    /// Reset the length to zero while keeping the capacity.
+    ///
+    /// It fails if there are `BytesSlice`s referring to the buffer, see [`AppendOnlyBytes::truncate`].
+    #[inline]
+    pub fn clear(&mut self) -> Result<(), StillShared> {
+        self.truncate(0)
+    }
+
+    /// Empty the buffer so that its allocation can be reused, e.g. by a buffer pool.
+    ///
+    /// Returns `None` if there are `BytesSlice`s referring to the buffer, in which case the
+    /// buffer should be dropped instead.
+    #[inline]
+    pub fn reset_for_reuse(mut self) -> Option<AppendOnlyBytes> {
+        self.clear().ok()?;
+        Some(self)
+    }
+
+    #[inline(always)]
+    fn is_unique(&self) -> bool {
+        Arc::strong_count(&self.raw) == 1
+    }
+
+    /// Whether there are `BytesSlice`s referring to the current allocation.
+    ///
+    /// When it's `false`, [`AppendOnlyBytes::truncate`], [`AppendOnlyBytes::clear`] and
+    /// [`AppendOnlyBytes::shrink_to_fit`] can take effect.
+    #[inline(always)]
+    pub fn is_shared(&self) -> bool {
+        !self.is_unique()
+    }
+
+    /// The number of `BytesSlice`s referring to the current allocation.
+    #[inline(always)]
+    pub fn slice_count(&self) -> usize {
+        Arc::strong_count(&self.raw) - 1
+    }
+
+    #[inline]
+    pub fn slice_str(&self, range: impl RangeBounds<usize>) -> Result<&str, core::str::Utf8Error> {
+        let (start, end) = get_range(range, self.len());
+        // SAFETY: data inside start..end is initialized
+        core::str::from_utf8(unsafe { self.raw.slice(start..end) })
+    }
+
+    /// Iterate over the lines of the buffer, separated by `\n` or `\r\n`, like [`str::lines`].
+    #[inline]
+    pub fn lines(&self) -> Lines<'_> {
+        Lines::new(self.as_bytes())
+    }
+
+    /// Like [`AppendOnlyBytes::slice_str`], but returns an error instead of panicking if the range
+    /// is out of bounds.
+    #[inline]
+    pub fn try_slice_str(&self, range: impl RangeBounds<usize>) -> Result<&str, SliceStrError> {
+        let (start, end) = try_get_range(range, self.len())?;
+        // SAFETY: data inside start..end is initialized
+        Ok(core::str::from_utf8(unsafe { self.raw.slice(start..end) })?)
+    }
+
+    /// A zero-copy view of `range` that shares the allocation.
+    ///
+    /// Dropping the result without using it is likely a bug, so it's `#[must_use]`:
+    ///
+    /// ```compile_fail
+    /// #![deny(unused_must_use)]
+    /// let mut bytes = append_only_bytes::AppendOnlyBytes::new();
+    /// bytes.push_slice(b"123");
+    /// bytes.slice(1..);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> BytesSlice {
+        let (start, end) = get_range(range, self.len());
+        BytesSlice::new(self.raw.clone(), start, end)
+    }
+
+    /// Split the bytes at `at` into two zero-copy slices of `0..at` and `at..len`.
+    ///
+    /// # Panics
+    ///
+    /// If `at > len`.
+    #[inline]
+    #[must_use]
+    pub fn split_off(&self, at: usize) -> (BytesSlice, BytesSlice) {
+        assert!(
+            at <= self.len,
+            "split index out of bounds: {} > {}",
+            at,
+            self.len
+        );
+        (self.slice(..at), self.slice(at..))
+    }
+
+    /// Like [`AppendOnlyBytes::slice`], but returns `None` if the range is out of bounds.
+    #[inline]
+    pub fn try_slice(&self, range: impl RangeBounds<usize>) -> Option<BytesSlice> {
+        let (start, end) = try_get_range(range, self.len()).ok()?;
+        Some(BytesSlice::new(self.raw.clone(), start, end))
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub fn to_slice(self) -> BytesSlice {
+        let end = self.len();
+        BytesSlice::new(self.raw, 0, end)
+    }
+
+    /// Convert into an immutable [`BytesSlice`] of `0..len` that shares the allocation, like
+    /// `bytes::BytesMut::freeze`. Same as [`AppendOnlyBytes::to_slice`].
+    ///
+    /// Nothing can be appended afterwards since the buffer is consumed. Use
+    /// [`AppendOnlyBytes::to_bytes_slice`] to keep appending.
+    #[inline(always)]
+    pub fn freeze(self) -> BytesSlice {
+        self.to_slice()
+    }
+
+    /// Like [`AppendOnlyBytes::to_slice`], but doesn't consume `self`.
+    #[inline(always)]
+    pub fn to_bytes_slice(&self) -> BytesSlice {
+        self.slice(..)
+    }
+
+    /// A read-only view of the whole buffer that shares the allocation. Same as
+    /// [`AppendOnlyBytes::to_bytes_slice`].
+    ///
+    /// Prefer it over `Clone` when you only need to read the data: `Clone` allocates
+    /// a new buffer and copies the data so that both buffers can be appended to.
+    #[inline(always)]
+    pub fn clone_as_slice(&self) -> BytesSlice {
+        self.to_bytes_slice()
+    }
+
+    #[inline]
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    /// Whether `slice` refers to the current allocation of this buffer.
+    #[inline(always)]
+    pub fn owns(&self, slice: &BytesSlice) -> bool {
+        core::ptr::eq(Arc::as_ptr(&self.raw), slice.raw.as_ptr())
+    }
+
+    /// Whether `slice` ends exactly at `len` of the current allocation, so the bytes pushed next
+    /// can be merged into it if no reallocation happens.
+    #[inline]
+    pub fn is_contiguous_with(&self, slice: &BytesSlice) -> bool {
+        self.owns(slice) && slice.end() == self.len
+    }
+
+    /// Convert into a `Vec<u8>` without copying.
+    ///
+    /// It fails and returns `self` if there are `BytesSlice`s referring to the same memory.
+    #[inline]
+    pub fn into_vec(self) -> Result<Vec<u8>, AppendOnlyBytes> {
+        let Self {
+            raw,
+            len,
+            fixed,
+            policy,
+            max_capacity,
+        } = self;
+        match Arc::try_unwrap(raw) {
+            // SAFETY: data inside `..len` is initialized
+            Ok(raw) => Ok(unsafe { raw.into_vec(len) }),
+            Err(raw) => Err(Self {
+                raw,
+                len,
+                fixed,
+                policy,
+                max_capacity,
+            }),
+        }
+    }
+}
+
+macro_rules! impl_push_num {
+    ($($ty:ty => $le:ident, $be:ident;)*) => {
+        impl AppendOnlyBytes {
+            $(
+                #[inline]
+                pub fn $le(&mut self, value: $ty) {
+                    self.push_slice(&value.to_le_bytes());
+                }
+
+                #[inline]
+                pub fn $be(&mut self, value: $ty) {
+                    self.push_slice(&value.to_be_bytes());
+                }
+            )*
+        }
+    };
+}
+
+impl_push_num! {
+    u16 => push_u16_le, push_u16_be;
+    u32 => push_u32_le, push_u32_be;
+    u64 => push_u64_le, push_u64_be;
+    i16 => push_i16_le, push_i16_be;
+    i32 => push_i32_le, push_i32_be;
+    i64 => push_i64_le, push_i64_be;
+    f32 => push_f32_le, push_f32_be;
+    f64 => push_f64_le, push_f64_be;
+}
+
+impl Default for AppendOnlyBytes {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::io::Write for AppendOnlyBytes {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.push_slice(buf);
+        Ok(buf.len())
+    }
+
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl AsRef<[u8]> for AppendOnlyBytes {
+    #[inline(always)]
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl<'a> IntoIterator for &'a AppendOnlyBytes {
+    type Item = &'a u8;
+    type IntoIter = core::slice::Iter<'a, u8>;
+
+    #[inline(always)]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl Extend<u8> for AppendOnlyBytes {
+    #[inline]
+    fn extend<T: IntoIterator<Item = u8>>(&mut self, iter: T) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for byte in iter {
+            self.push(byte);
+        }
+    }
+}
+
+impl<'a> Extend<&'a u8> for AppendOnlyBytes {
+    #[inline]
+    fn extend<T: IntoIterator<Item = &'a u8>>(&mut self, iter: T) {
+        self.extend(iter.into_iter().copied())
+    }
+}
+
+impl FromIterator<u8> for AppendOnlyBytes {
+    #[inline]
+    fn from_iter<T: IntoIterator<Item = u8>>(iter: T) -> Self {
+        let mut bytes = Self::new();
+        bytes.extend(iter);
+        bytes
+    }
+}
+
+impl<'a> FromIterator<&'a u8> for AppendOnlyBytes {
+    #[inline]
+    fn from_iter<T: IntoIterator<Item = &'a u8>>(iter: T) -> Self {
+        let mut bytes = Self::new();
+        bytes.extend(iter);
+        bytes
+    }
+}
+
+impl core::fmt::Write for AppendOnlyBytes {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+
+    #[inline]
+    fn write_char(&mut self, c: char) -> core::fmt::Result {
+        let mut buf = [0; 4];
+        self.push_str(c.encode_utf8(&mut buf));
+        Ok(())
+    }
+}
+
+#[inline(always)]
+fn find_byte(haystack: &[u8], needle: u8) -> Option<usize> {
+    #[cfg(feature = "memchr")]
+    return memchr::memchr(needle, haystack);
+    #[cfg(not(feature = "memchr"))]
+    haystack.iter().position(|&x| x == needle)
+}
+
+#[inline(always)]
+fn count_byte(haystack: &[u8], needle: u8) -> usize {
+    #[cfg(feature = "memchr")]
+    return memchr::memchr_iter(needle, haystack).count();
+    #[cfg(not(feature = "memchr"))]
+    haystack.iter().filter(|&&x| x == needle).count()
+}
+
+#[inline(always)]
+fn get_range(range: impl RangeBounds<usize>, max_len: usize) -> (usize, usize) {
+    let start = match range.start_bound() {
+        core::ops::Bound::Included(&v) => v,
+        core::ops::Bound::Excluded(&v) => v + 1,
+        core::ops::Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        core::ops::Bound::Included(&v) => v + 1,
+        core::ops::Bound::Excluded(&v) => v,
+        core::ops::Bound::Unbounded => max_len,
     };
+    assert!(start <= end);
+    assert!(end <= max_len);
+    (start, end)
+}
+
+#[inline(always)]
+fn try_get_range(
+    range: impl RangeBounds<usize>,
+    max_len: usize,
+) -> Result<(usize, usize), RangeError> {
+    let start = match range.start_bound() {
+        core::ops::Bound::Included(&v) => v,
+        core::ops::Bound::Excluded(&v) => v.checked_add(1).ok_or(RangeError::Overflow)?,
+        core::ops::Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        core::ops::Bound::Included(&v) => v.checked_add(1).ok_or(RangeError::Overflow)?,
+        core::ops::Bound::Excluded(&v) => v,
+        core::ops::Bound::Unbounded => max_len,
+    };
+    if start > end {
+        return Err(RangeError::Inverted { start, end });
+    }
+    if end > max_len {
+        return Err(RangeError::OutOfBounds { end, len: max_len });
+    }
+    Ok((start, end))
+}
+
+impl<I: SliceIndex<[u8]>> Index<I> for AppendOnlyBytes {
+    type Output = I::Output;
+
+    #[inline]
+    fn index(&self, index: I) -> &Self::Output {
+        // SAFETY: data inside 0..self.len is initialized
+        unsafe { Index::index(self.raw.slice(..self.len), index) }
+    }
+}
+
+// SAFETY: It's Send & Sync because it doesn't have interior mutability. All the accessible data in this type will never be changed.
+unsafe impl Send for BytesSlice {}
+// SAFETY: It's Send & Sync because it doesn't have interior mutability. All the accessible data in this type will never be changed.
+unsafe impl Sync for BytesSlice {}
+
+/// The type of the offsets of `BytesSlice`. If both `u16_range` and `u32_range` are enabled,
+/// the wider one is used.
+///
+/// The `Arc` is stored by [`PackedArc`] with an alignment of 4, so `u16` offsets are not padded
+/// to 8 bytes on 64-bit targets.
+#[cfg(not(any(feature = "u32_range", feature = "u16_range")))]
+type Int = usize;
+#[cfg(feature = "u32_range")]
+type Int = u32;
+#[cfg(all(feature = "u16_range", not(feature = "u32_range")))]
+type Int = u16;
+
+/// Conversion between `usize` and the offset types.
+///
+/// It's sealed in the crate so that the offset type can only be one of `usize`, `u32` and `u16`.
+trait Offset: Copy {
+    /// The max offset in `usize`
+    #[cfg_attr(
+        not(any(feature = "u32_range", feature = "u16_range")),
+        allow(dead_code)
+    )]
+    const MAX: usize;
+    /// It truncates `value` if it's greater than `MAX`.
+    fn from_usize(value: usize) -> Self;
+    fn to_usize(self) -> usize;
+}
+
+macro_rules! impl_offset {
+    ($($ty:ty),*) => {
+        $(
+            #[allow(clippy::unnecessary_cast)]
+            impl Offset for $ty {
+                const MAX: usize = <$ty>::MAX as usize;
+
+                #[inline(always)]
+                fn from_usize(value: usize) -> Self {
+                    value as $ty
+                }
+
+                #[inline(always)]
+                fn to_usize(self) -> usize {
+                    self as usize
+                }
+            }
+        )*
+    };
+}
+
+impl_offset!(usize, u32, u16);
+
+impl BytesSlice {
+    #[inline(always)]
+    fn new(raw: impl Into<PackedArc>, start: usize, end: usize) -> Self {
+        // The offsets would be truncated silently by the conversions below. The buffer cannot
+        // grow beyond it, but it may adopt a larger `Vec` or be created with a larger capacity.
+        #[cfg(any(feature = "u32_range", feature = "u16_range"))]
+        assert!(
+            start <= <Int as Offset>::MAX && end <= <Int as Offset>::MAX,
+            "offset out of range: {}..{} > {}",
+            start,
+            end,
+            <Int as Offset>::MAX
+        );
+        Self {
+            raw: raw.into(),
+            start: Int::from_usize(start),
+            end: Int::from_usize(end),
+        }
+    }
+
+    #[inline(always)]
+    pub fn empty() -> Self {
+        Self {
+            raw: empty_raw().into(),
+            start: 0,
+            end: 0,
+        }
+    }
+
+    #[inline(always)]
+    fn bytes(&self) -> &[u8] {
+        // SAFETY: data inside this range is guaranteed to be initialized
+        unsafe { self.raw.slice(self.start()..self.end()) }
+    }
+
+    #[inline(always)]
+    pub fn as_bytes(&self) -> &[u8] {
+        // SAFETY: data inside this range is guaranteed to be initialized
+        unsafe { self.raw.slice(self.start()..self.end()) }
+    }
+
+    /// The pointer to the first initialized byte.
+    ///
+    /// The `len()` bytes behind it stay valid and unchanged as long as `self` is alive and
+    /// not mutated. They must not be written through this pointer.
+    #[inline(always)]
+    pub fn as_ptr(&self) -> *const u8 {
+        self.as_bytes().as_ptr()
+    }
+
+    /// # Safety
+    ///
+    /// The bytes must be valid UTF-8.
+    #[inline(always)]
+    pub unsafe fn as_str_unchecked(&self) -> &str {
+        // SAFETY: Caller must ensure that the bytes are valid UTF-8
+        unsafe { core::str::from_utf8_unchecked(self.as_bytes()) }
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> core::slice::Iter<'_, u8> {
+        self.as_bytes().iter()
+    }
+
+    /// The first byte. It's not named `first`, so the deref to [`slice::first`] still works.
+    #[inline(always)]
+    pub fn first_byte(&self) -> Option<u8> {
+        self.as_bytes().first().copied()
+    }
+
+    #[inline(always)]
+    pub fn last_byte(&self) -> Option<u8> {
+        self.as_bytes().last().copied()
+    }
+
+    #[inline(always)]
+    pub fn get_byte(&self, index: usize) -> Option<u8> {
+        self.as_bytes().get(index).copied()
+    }
+
+    #[inline(always)]
+    pub fn starts_with(&self, prefix: &[u8]) -> bool {
+        self.as_bytes().starts_with(prefix)
+    }
+
+    #[inline(always)]
+    pub fn ends_with(&self, suffix: &[u8]) -> bool {
+        self.as_bytes().ends_with(suffix)
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.end() - self.start()
+    }
+
+    #[allow(clippy::arc_with_non_send_sync)]
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let new = RawBytes::with_capacity(bytes.len());
+        // SAFETY: raw and new have at least self.len capacity
+        unsafe {
+            core::ptr::copy_nonoverlapping(bytes.as_ptr(), new.ptr(), bytes.len());
+        }
+        new.mark_initialized(bytes.len());
+
+        Self::new(Arc::new(new), 0, bytes.len())
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.end == self.start
+    }
+
+    #[inline(always)]
+    #[must_use]
+    pub fn slice_clone(&self, range: impl core::ops::RangeBounds<usize>) -> Self {
+        let (start, end) = get_range(range, self.len());
+        Self::new(self.raw.clone(), self.start() + start, self.start() + end)
+    }
+
+    /// The index of the first occurrence of `needle`, relative to the start of the slice.
+    #[inline]
+    pub fn find_byte(&self, needle: u8) -> Option<usize> {
+        find_byte(self.as_bytes(), needle)
+    }
+
+    #[inline]
+    pub fn contains_byte(&self, needle: u8) -> bool {
+        find_byte(self.as_bytes(), needle).is_some()
+    }
+
+    /// The number of occurrences of `needle`.
+    #[inline]
+    pub fn count_byte(&self, needle: u8) -> usize {
+        count_byte(self.as_bytes(), needle)
+    }
+
+    #[inline]
+    pub fn eq_ignore_ascii_case(&self, other: &[u8]) -> bool {
+        self.as_bytes().eq_ignore_ascii_case(other)
+    }
+
+    /// A zero-copy slice without the leading and trailing ASCII whitespace. Unlike
+    /// [`slice::trim_ascii`], it shares the allocation.
+    #[inline]
+    pub fn trim_ascii_shared(&self) -> BytesSlice {
+        self.trim_ascii_start_shared().trim_ascii_end_shared()
+    }
+
+    /// A zero-copy slice without the leading ASCII whitespace.
+    #[inline]
+    pub fn trim_ascii_start_shared(&self) -> BytesSlice {
+        let bytes = self.as_bytes();
+        self.slice_clone(bytes.len() - bytes.trim_ascii_start().len()..)
+    }
+
+    /// A zero-copy slice without the trailing ASCII whitespace.
+    #[inline]
+    pub fn trim_ascii_end_shared(&self) -> BytesSlice {
+        self.slice_clone(..self.as_bytes().trim_ascii_end().len())
+    }
+
+    /// Append the ASCII lowercase copy of the bytes to `out`.
+    #[inline]
+    pub fn to_ascii_lowercase_into(&self, out: &mut AppendOnlyBytes) {
+        out.extend(self.iter().map(u8::to_ascii_lowercase));
+    }
+
+    /// The index of the first occurrence of `needle`, relative to the start of the slice.
+    ///
+    /// An empty `needle` is found at index 0.
+    #[inline]
+    pub fn find(&self, needle: &[u8]) -> Option<usize> {
+        if needle.is_empty() {
+            return Some(0);
+        }
+
+        #[cfg(feature = "memchr")]
+        return memchr::memmem::find(self.as_bytes(), needle);
+        #[cfg(not(feature = "memchr"))]
+        self.as_bytes()
+            .windows(needle.len())
+            .position(|window| window == needle)
+    }
+
+    /// Split into zero-copy chunks of `size` bytes, like [`slice::chunks`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is 0.
+    #[inline]
+    pub fn chunks_shared(&self, size: usize) -> Chunks<'_> {
+        Chunks::new(self, size)
+    }
+
+    /// Overlapping zero-copy windows of `size` bytes, like [`slice::windows`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is 0.
+    #[inline]
+    pub fn windows_shared(&self, size: usize) -> Windows<'_> {
+        Windows::new(self, size)
+    }
+
+    /// Split into zero-copy slices of maximal runs of the same byte, e.g. for RLE encoding.
+    #[inline]
+    pub fn runs(&self) -> Runs<'_> {
+        Runs::new(self)
+    }
+
+    /// Split by `delim` into zero-copy slices, like [`slice::split`].
+    #[inline]
+    pub fn split_shared(&self, delim: u8) -> Split<'_> {
+        Split::new(self, delim)
+    }
+
+    /// Same as [`BytesSlice::split_shared`].
+    #[inline]
+    pub fn split_on(&self, delim: u8) -> Split<'_> {
+        self.split_shared(delim)
+    }
+
+    /// Create a new slice by the offsets in the original buffer.
+    ///
+    /// Returns `None` if the range is not inside `self.start()..self.end()`.
+    #[inline]
+    pub fn subslice_abs(&self, range: Range<usize>) -> Option<BytesSlice> {
+        if range.start < self.start() || range.end > self.end() || range.start > range.end {
+            return None;
+        }
+
+        Some(Self::new(self.raw.clone(), range.start, range.end))
+    }
+
+    /// Split into `[0, mid)` and `[mid, len)` without copying. Unlike [`slice::split_at`], both
+    /// halves share the allocation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mid > len`.
+    #[inline]
+    pub fn split_at_shared(&self, mid: usize) -> (BytesSlice, BytesSlice) {
+        assert!(mid <= self.len(), "mid > len: {} > {}", mid, self.len());
+        (self.slice_clone(..mid), self.slice_clone(mid..))
+    }
+
+    #[inline(always)]
+    pub fn slice_(&mut self, range: impl core::ops::RangeBounds<usize>) {
+        let (start, end) = get_range(range, self.len());
+        self.end = Int::from_usize(self.start() + end);
+        self.start = Int::from_usize(self.start() + start);
+    }
+
+    /// Copy the bytes into a new `Arc<[u8]>`.
+    ///
+    /// It always copies, since the allocation of `BytesSlice` cannot be shared as `Arc<[u8]>`.
+    #[inline]
+    pub fn to_arc_slice(&self) -> Arc<[u8]> {
+        Arc::from(self.as_bytes())
+    }
+
+    /// Copy the bytes starting at `offset` into `dst` and return the number of bytes copied.
+    /// Unlike `bytes::Buf::copy_to_slice`, it doesn't consume the bytes.
+    ///
+    /// It copies fewer than `dst.len()` bytes if the slice ends earlier, and nothing if `offset`
+    /// is out of range.
+    #[inline]
+    pub fn copy_from_offset(&self, offset: usize, dst: &mut [u8]) -> usize {
+        let src = self.as_bytes().get(offset..).unwrap_or_default();
+        let n = src.len().min(dst.len());
+        dst[..n].copy_from_slice(&src[..n]);
+        n
+    }
+
+    #[inline]
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    /// Whether anything else, i.e. another `BytesSlice` or the `AppendOnlyBytes` that created
+    /// it, refers to the same allocation.
+    #[inline(always)]
+    pub fn is_shared(&self) -> bool {
+        self.raw.strong_count() > 1
+    }
+
+    /// The number of the other references to the allocation, including the `AppendOnlyBytes`
+    /// that created it if it's still alive.
+    #[inline(always)]
+    pub fn slice_count(&self) -> usize {
+        self.raw.strong_count() - 1
+    }
+
+    #[inline(always)]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        core::ptr::eq(self.raw.as_ptr(), other.raw.as_ptr())
+    }
+
+    #[inline(always)]
+    pub fn can_merge(&self, other: &Self) -> bool {
+        self.ptr_eq(other) && self.end == other.start
+    }
+
+    #[inline(always)]
+    pub fn try_merge(&mut self, other: &Self) -> Result<(), MergeFailed> {
+        if self.can_merge(other) {
+            self.end = other.end;
+            Ok(())
+        } else {
+            Err(MergeFailed)
+        }
+    }
+
+    /// Like [`BytesSlice::try_merge`], but returns a new slice without changing `self`.
+    #[inline(always)]
+    #[must_use]
+    pub fn merged(&self, other: &Self) -> Option<BytesSlice> {
+        if self.can_merge(other) {
+            Some(Self::new(self.raw.clone(), self.start(), other.end()))
+        } else {
+            None
+        }
+    }
+
+    /// A new slice covering `a` immediately followed by `b`, like [`BytesSlice::merged`] but
+    /// returns the same error as [`BytesSlice::try_merge`].
+    #[inline]
+    pub fn join(a: &BytesSlice, b: &BytesSlice) -> Result<BytesSlice, MergeFailed> {
+        a.merged(b).ok_or(MergeFailed)
+    }
+
+    /// Whether `other` is right before `self` in the same buffer.
+    #[inline(always)]
+    pub fn can_merge_before(&self, other: &Self) -> bool {
+        self.ptr_eq(other) && other.end == self.start
+    }
+
+    /// Extend `self` backwards to cover `other`, if `other` is right before `self`.
+    #[inline(always)]
+    pub fn try_merge_before(&mut self, other: &Self) -> Result<(), MergeFailed> {
+        if self.can_merge_before(other) {
+            self.start = other.start;
+            Ok(())
+        } else {
+            Err(MergeFailed)
+        }
+    }
+
+    /// Merge the adjacent mergeable slices in place.
+    pub fn merge_all(slices: &mut Vec<BytesSlice>) {
+        if slices.is_empty() {
+            return;
+        }
+
+        let mut last = 0;
+        for i in 1..slices.len() {
+            if slices[last].can_merge(&slices[i]) {
+                slices[last].end = slices[i].end;
+            } else {
+                last += 1;
+                slices.swap(last, i);
+            }
+        }
+        slices.truncate(last + 1);
+    }
+
+    /// The whole slice as `&str`, like `slice_str(..)`.
+    #[inline]
+    pub fn as_str(&self) -> Result<&str, core::str::Utf8Error> {
+        core::str::from_utf8(self.as_bytes())
+    }
+
+    #[inline]
+    pub fn slice_str(&self, range: impl RangeBounds<usize>) -> Result<&str, core::str::Utf8Error> {
+        let (start, end) = get_range(range, self.len());
+        core::str::from_utf8(&self.deref()[start..end])
+    }
+
+    /// Like [`BytesSlice::slice_str`], but returns an error instead of panicking if the range
+    /// is out of bounds.
+    #[inline]
+    pub fn try_slice_str(&self, range: impl RangeBounds<usize>) -> Result<&str, SliceStrError> {
+        let (start, end) = try_get_range(range, self.len())?;
+        Ok(core::str::from_utf8(&self.deref()[start..end])?)
+    }
+
+    #[inline(always)]
+    pub fn start(&self) -> usize {
+        self.start.to_usize()
+    }
+
+    #[inline(always)]
+    pub fn end(&self) -> usize {
+        self.end.to_usize()
+    }
+}
+
+/// The zero-capacity buffer shared by all the empty slices. It's created on the first call and
+/// never freed. It only needs atomics, so it works without `std`.
+fn empty_raw() -> Arc<RawBytes> {
+    use core::sync::atomic::{AtomicPtr, Ordering};
+
+    static EMPTY: AtomicPtr<RawBytes> = AtomicPtr::new(core::ptr::null_mut());
+    let mut ptr = EMPTY.load(Ordering::Acquire);
+    if ptr.is_null() {
+        #[allow(clippy::arc_with_non_send_sync)]
+        let new = Arc::into_raw(Arc::new(RawBytes::with_capacity(0))).cast_mut();
+        ptr = match EMPTY.compare_exchange(
+            core::ptr::null_mut(),
+            new,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => new,
+            Err(existing) => {
+                // SAFETY: `new` is created by `Arc::into_raw` above and it's not published
+                drop(unsafe { Arc::from_raw(new) });
+                existing
+            }
+        };
+    }
+
+    // SAFETY: `ptr` is created by `Arc::into_raw`, and `EMPTY` holds a strong count forever
+    unsafe {
+        Arc::increment_strong_count(ptr);
+        Arc::from_raw(ptr)
+    }
+}
+
+#[derive(Debug)]
+pub struct MergeFailed;
+
+#[derive(Debug)]
+pub struct StillShared;
+
+#[derive(Debug)]
+pub struct CapacityExceeded;
+
+#[derive(Debug)]
+pub struct CapacityLimitExceeded;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeError {
+    /// `start > end`
+    Inverted { start: usize, end: usize },
+    /// `end > len`
+    OutOfBounds { end: usize, len: usize },
+    /// The inclusive bound overflows `usize`
+    Overflow,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SliceStrError {
+    Range(RangeError),
+    Utf8(core::str::Utf8Error),
+}
+
+impl From<RangeError> for SliceStrError {
+    fn from(e: RangeError) -> Self {
+        Self::Range(e)
+    }
+}
+
+impl From<core::str::Utf8Error> for SliceStrError {
+    fn from(e: core::str::Utf8Error) -> Self {
+        Self::Utf8(e)
+    }
+}
+
+impl From<AppendOnlyBytes> for BytesSlice {
+    #[inline(always)]
+    fn from(bytes: AppendOnlyBytes) -> Self {
+        bytes.to_slice()
+    }
+}
+
+impl From<&[u8]> for BytesSlice {
+    #[inline]
+    fn from(bytes: &[u8]) -> Self {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl From<Vec<u8>> for BytesSlice {
+    /// Adopt the memory of `vec` without copying.
+    #[inline]
+    fn from(vec: Vec<u8>) -> Self {
+        AppendOnlyBytes::from_vec(vec).to_slice()
+    }
+}
+
+impl TryFrom<BytesSlice> for String {
+    type Error = alloc::string::FromUtf8Error;
+
+    #[inline]
+    fn try_from(slice: BytesSlice) -> Result<Self, Self::Error> {
+        String::from_utf8(slice.to_vec())
+    }
+}
+
+impl<I: SliceIndex<[u8]>> Index<I> for BytesSlice {
+    type Output = I::Output;
+
+    #[inline]
+    fn index(&self, index: I) -> &Self::Output {
+        Index::index(self.bytes(), index)
+    }
+}
+
+impl Deref for BytesSlice {
+    type Target = [u8];
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        self.bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::prelude::rust_2021::*;
+    use std::{
+        dbg,
+        sync::mpsc::{self, Receiver, Sender},
+        thread, vec,
+    };
+
+    use super::*;
+
+    fn hash_of(v: &impl Hash) -> u64 {
+        use std::hash::Hasher;
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        v.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test() {
+        let mut a = AppendOnlyBytes::new();
+        let mut count = 0;
+        for _ in 0..100 {
+            a.push(8);
+            count += 1;
+            assert_eq!(a.len(), count);
+        }
+
+        for _ in 0..100 {
+            a.push_slice(&[1, 2]);
+            count += 2;
+            assert_eq!(a.len(), count);
+        }
+    }
+
+    #[test]
+    fn it_works() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_str("123");
+        assert_eq!(a.slice_str(0..1).unwrap(), "1");
+        let b = a.slice(..);
+        for _ in 0..10 {
+            a.push_str("456");
+            dbg!(a.slice_str(..).unwrap());
+        }
+        let c = a.slice(..);
+        drop(a);
+        dbg!(c.slice_str(..).unwrap());
+        assert_eq!(c.len(), 33);
+        assert_eq!(c.slice_str(..6).unwrap(), "123456");
+
+        assert_eq!(b.deref(), "123".as_bytes());
+    }
+
+    #[test]
+    fn push_large() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(&[1; 10000]);
+        assert_eq!(a.as_bytes(), &[1; 10000]);
+    }
+
+    #[test]
+    fn threads() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_str("123");
+        assert_eq!(a.slice_str(0..1).unwrap(), "1");
+        let (tx, rx): (Sender<AppendOnlyBytes>, Receiver<AppendOnlyBytes>) = mpsc::channel();
+        let b = a.slice(..);
+        let t = thread::spawn(move || {
+            for _ in 0..10 {
+                a.push_str("456");
+                dbg!(a.slice_str(..).unwrap());
+            }
+            let c = a.slice(..);
+            tx.send(a).unwrap();
+            dbg!(c.slice_str(..).unwrap());
+            assert_eq!(c.len(), 33);
+            assert_eq!(c.slice_str(..6).unwrap(), "123456");
+        });
+        let t1 = thread::spawn(move || {
+            assert_eq!(b.deref(), "123".as_bytes());
+            for _ in 0..10 {
+                let c = b.slice_clone(0..1);
+                assert_eq!(c.deref(), "1".as_bytes());
+            }
+        });
+
+        let a = rx.recv().unwrap();
+        assert_eq!(a.len(), 33);
+        assert_eq!(&a[..6], "123456".as_bytes());
+        t.join().unwrap();
+        t1.join().unwrap()
+    }
+
+    #[test]
+    fn from_bytes() {
+        let a = BytesSlice::from_bytes(b"123");
+        assert_eq!(a.len(), 3);
+        assert_eq!(a.slice_str(..).unwrap(), "123");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn io_write() {
+        use std::io::Write;
+        let mut a = AppendOnlyBytes::new();
+        write!(a, "{}", 123).unwrap();
+        a.write_all(b"456").unwrap();
+        a.flush().unwrap();
+        assert_eq!(a.as_bytes(), b"123456");
+    }
+
+    #[test]
+    fn fmt_write() {
+        use std::fmt::Write;
+        let mut a = AppendOnlyBytes::new();
+        for i in 0..3 {
+            writeln!(a, "line {}", i).unwrap();
+        }
+        a.write_char('é').unwrap();
+        assert_eq!(a.slice_str(..).unwrap(), "line 0\nline 1\nline 2\né");
+    }
+
+    #[test]
+    fn into_vec() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(&[1, 2, 3]);
+        let ptr = a.as_bytes().as_ptr();
+        let v = a.into_vec().unwrap();
+        assert_eq!(v, vec![1, 2, 3]);
+        assert_eq!(v.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn into_vec_shared() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(&[1, 2, 3]);
+        let b = a.slice(1..);
+        let a = a.into_vec().unwrap_err();
+        assert_eq!(a.as_bytes(), &[1, 2, 3]);
+        drop(b);
+        assert_eq!(a.into_vec().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn from_vec() {
+        let mut v = Vec::with_capacity(4);
+        v.extend_from_slice(&[1, 2, 3]);
+        let mut a = AppendOnlyBytes::from_vec(v);
+        assert_eq!(a.len(), 3);
+        assert_eq!(a.capacity(), 4);
+        let b = a.slice(..);
+        a.push_slice(&[4, 5, 6]);
+        assert!(a.capacity() >= 6);
+        assert_eq!(a.as_bytes(), &[1, 2, 3, 4, 5, 6]);
+        assert_eq!(b.as_bytes(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn reserve_exact() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(&[1, 2, 3]);
+        a.reserve_exact(100);
+        assert_eq!(a.capacity(), 103);
+        a.reserve_exact(10);
+        assert_eq!(a.capacity(), 103);
+        assert_eq!(a.as_bytes(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn extend() {
+        let mut a = AppendOnlyBytes::new();
+        a.extend(0u8..200);
+        assert_eq!(a.as_bytes(), (0u8..200).collect::<Vec<_>>().as_slice());
+        a.extend((0u8..10).filter(|x| x % 2 == 0));
+        a.extend(&[1, 2]);
+        assert_eq!(a.len(), 207);
+        assert_eq!(&a[200..], &[0, 2, 4, 6, 8, 1, 2]);
+    }
+
+    #[test]
+    fn from_iter() {
+        let a: AppendOnlyBytes = (0u8..100).map(|x| x * 2).collect();
+        assert_eq!(
+            a.as_bytes(),
+            (0u8..100).map(|x| x * 2).collect::<Vec<_>>().as_slice()
+        );
+        let b: AppendOnlyBytes = [1u8, 2, 3].iter().collect();
+        assert_eq!(b.as_bytes(), &[1, 2, 3]);
+    }
+
+    #[test]
+    #[allow(clippy::mutable_key_type)]
+    fn as_ref_and_borrow() {
+        fn len_of(bytes: impl AsRef<[u8]>) -> usize {
+            bytes.as_ref().len()
+        }
+
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(&[1, 2, 3]);
+        let b = a.slice(1..);
+        assert_eq!(len_of(&a), 3);
+        assert_eq!(len_of(&b), 2);
+
+        let mut map = std::collections::BTreeMap::new();
+        map.insert(b, 1);
+        assert_eq!(map.get(&[2u8, 3][..]), Some(&1));
+        assert_eq!(map.get(&[1u8, 2][..]), None);
+    }
+
+    #[test]
+    // The debug-only initialized length of `RawBytes` is atomic, but it doesn't affect the hash
+    #[allow(clippy::mutable_key_type)]
+    fn hash_slice() {
+        use std::collections::HashSet;
+
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(&[0, 1, 2, 3]);
+        let x = a.slice(1..);
+        let y = BytesSlice::from_bytes(&[1, 2, 3]);
+        assert_eq!(hash_of(&x), hash_of(&y));
+        let set: HashSet<_> = [x, y].into_iter().collect();
+        assert_eq!(set.len(), 1);
+        assert!(set.contains(&[1u8, 2, 3][..]));
+    }
+
+    #[test]
+    fn hash_bytes() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(&[1, 2, 3]);
+        let b = a.clone();
+        assert_eq!(a, b);
+        assert_eq!(hash_of(&a), hash_of(&b));
+        a.push(4);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn cross_type_eq() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(b"123");
+        let b = a.slice(..);
+        assert_eq!(a, b);
+        assert_eq!(b, a);
+        assert_eq!(b, b"123".as_slice());
+        assert_eq!(b"123".as_slice(), b);
+        assert_eq!(b, *b"123".as_slice());
+        assert_eq!(*b"123".as_slice(), b);
+        assert_eq!(a, b"123".as_slice());
+        assert_eq!(b"123".as_slice(), a);
+        assert_eq!(a, *b"123".as_slice());
+        assert_eq!(*b"123".as_slice(), a);
+        assert_ne!(a.slice(1..), a);
+        assert_ne!(a.slice(1..), b"123".as_slice());
+    }
+
+    #[test]
+    fn display() {
+        let a = BytesSlice::from_bytes(b"hello");
+        assert_eq!(a.to_string(), "hello");
+        let b = BytesSlice::from_bytes(&[b'a', 0xff, b'b']);
+        assert_eq!(b.to_string(), "a\u{FFFD}b");
+    }
+
+    #[test]
+    fn iter() {
+        let a: AppendOnlyBytes = (1u8..=10).collect();
+        let b = a.slice(5..);
+        assert_eq!(a.iter().copied().map(u32::from).sum::<u32>(), 55);
+        assert_eq!(b.iter().copied().map(u32::from).sum::<u32>(), 40);
+        assert_eq!(b.iter().rev().position(|&x| x == 9), Some(1));
+        let mut sum = 0;
+        for x in &a {
+            sum += *x as u32;
+        }
+        for x in &b {
+            sum += *x as u32;
+        }
+        assert_eq!(sum, 95);
+    }
+
+    #[test]
+    fn split_at_shared() {
+        let a = BytesSlice::from_bytes(b"header:body");
+        let (head, body) = a.split_at_shared(7);
+        assert!(head.ptr_eq(&a));
+        assert!(body.ptr_eq(&a));
+        assert_eq!(head.as_bytes(), b"header:");
+        assert_eq!(body.as_bytes(), b"body");
+        assert_eq!([head.as_bytes(), body.as_bytes()].concat(), a.as_bytes());
+        let (head, body) = a.split_at_shared(a.len());
+        assert_eq!(head, a);
+        assert!(body.is_empty());
+        let (head, _): (&[u8], &[u8]) = a.split_at(7);
+        assert_eq!(head, b"header:");
+    }
+
+    #[test]
+    #[should_panic]
+    fn split_at_shared_out_of_bounds() {
+        let a = BytesSlice::from_bytes(b"123");
+        a.split_at_shared(4);
+    }
+
+    #[test]
+    fn empty() {
+        let a = BytesSlice::empty();
+        let b = BytesSlice::empty();
+        assert!(a.is_empty());
+        assert_eq!(a, b);
+        assert!(a.ptr_eq(&b));
+        assert_eq!(a, BytesSlice::from_bytes(&[]));
+        assert_eq!(a.as_bytes(), &[] as &[u8]);
+    }
+
+    #[test]
+    fn truncate() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(&[1, 2, 3]);
+        a.truncate(1).unwrap();
+        assert_eq!(a.as_bytes(), &[1]);
+        a.truncate(10).unwrap();
+        assert_eq!(a.len(), 1);
+        a.push_slice(&[4, 5]);
+        assert_eq!(a.as_bytes(), &[1, 4, 5]);
+    }
+
+    #[test]
+    fn truncate_shared() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(&[1, 2, 3]);
+        let b = a.slice(1..);
+        assert!(a.truncate(1).is_err());
+        assert_eq!(a.len(), 3);
+        a.truncate(3).unwrap();
+        drop(b);
+        a.truncate(1).unwrap();
+        assert_eq!(a.as_bytes(), &[1]);
+    }
+
+    #[test]
+    fn spare_capacity_mut() {
+        let mut a = AppendOnlyBytes::new();
+        a.push(1);
+        let b = a.slice(..);
+        a.reserve(10);
+        let spare = a.spare_capacity_mut();
+        assert!(spare.len() >= 10);
+        for (i, x) in spare[..3].iter_mut().enumerate() {
+            x.write(i as u8 + 2);
+        }
+        // SAFETY: the first 3 bytes of the spare capacity are initialized
+        unsafe { a.set_len(4) };
+        assert_eq!(a.as_bytes(), &[1, 2, 3, 4]);
+        assert_eq!(b.as_bytes(), &[1]);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn io_read() {
+        use std::io::Read;
+        let data: Vec<u8> = (0u8..100).collect();
+        let mut a = BytesSlice::from_bytes(&data);
+        let mut out = Vec::new();
+        let mut buf = [0; 7];
+        loop {
+            let n = a.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            out.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(out, data);
+        assert!(a.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn append_from_reader() {
+        let mut a = AppendOnlyBytes::new();
+        a.push(0);
+        let mut src: &[u8] = &[1, 2, 3, 4, 5];
+        assert_eq!(a.append_from_reader(&mut src, 3).unwrap(), 3);
+        assert_eq!(a.append_from_reader(&mut src, 10).unwrap(), 2);
+        assert_eq!(a.append_from_reader(&mut src, 10).unwrap(), 0);
+        assert_eq!(a.as_bytes(), &[0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn concat() {
+        let a = AppendOnlyBytes::from_slices(&[b"12", b"", b"345"]);
+        assert_eq!(a.as_bytes(), [&b"12"[..], b"", b"345"].concat());
+        assert_eq!(a.capacity(), 5);
+        let b = AppendOnlyBytes::concat_slices(&[a.slice(3..), BytesSlice::empty(), a.slice(..2)]);
+        assert_eq!(b.as_bytes(), [&a[3..], &a[..2]].concat());
+        assert_eq!(b.capacity(), 4);
+    }
+
+    #[test]
+    fn shrink_to_fit() {
+        let mut a = AppendOnlyBytes::with_capacity(112);
+        a.push_slice(&[1, 2, 3]);
+        let b = a.slice(..);
+        a.shrink_to_fit();
+        assert_eq!(a.capacity(), 112);
+        drop(b);
+        a.shrink_to_fit();
+        assert_eq!(a.capacity(), 3);
+        assert_eq!(a.as_bytes(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn remaining_capacity() {
+        let mut a = AppendOnlyBytes::new();
+        a.reserve_exact(4);
+        assert_eq!(a.remaining_capacity(), 4);
+        while a.remaining_capacity() > 0 {
+            a.push(1);
+        }
+        assert_eq!(a.len(), 4);
+        a.push(1);
+        assert!(a.capacity() > 4);
+        assert_eq!(a.remaining_capacity(), a.capacity() - 5);
+    }
+
+    #[test]
+    fn will_realloc() {
+        let mut a = AppendOnlyBytes::new();
+        a.reserve_exact(4);
+        a.push_slice(&[1, 2]);
+        assert!(!a.will_realloc(2));
+        assert!(a.will_realloc(3));
+        let b = a.slice(..);
+        a.push_slice(&[3, 4]);
+        assert!(b.can_merge(&a.slice(2..)));
+        assert!(a.will_realloc(1));
+        a.push(5);
+        assert!(!b.can_merge(&a.slice(2..)));
+    }
+
+    #[test]
+    fn try_push_slice() {
+        let mut a = AppendOnlyBytes::new();
+        a.reserve_exact(4);
+        a.try_push_slice(&[1, 2, 3]).unwrap();
+        a.try_push_slice(&[4]).unwrap();
+        assert!(a.try_push_slice(&[5]).is_err());
+        assert_eq!(a.len(), 4);
+        assert_eq!(a.capacity(), 4);
+        a.try_push_slice(&[]).unwrap();
+        assert_eq!(a.as_bytes(), &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn fixed_capacity() {
+        let mut a = AppendOnlyBytes::fixed_with_capacity(4);
+        assert!(a.is_fixed());
+        let ptr = a.as_bytes().as_ptr();
+        a.push_slice(&[1, 2]);
+        let b = a.slice(..);
+        a.push(3);
+        assert!(a.try_push_slice(&[4, 5]).is_err());
+        a.try_push_slice(&[4]).unwrap();
+        a.shrink_to_fit();
+        assert_eq!(a.as_bytes().as_ptr(), ptr);
+        assert_eq!(a.capacity(), 4);
+        assert!(b.can_merge(&a.slice(2..)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn fixed_capacity_exceeded() {
+        let mut a = AppendOnlyBytes::fixed_with_capacity(4);
+        a.push_slice(&[1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn owns() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(&[1, 2, 3]);
+        let b = a.clone();
+        let x = a.slice(1..);
+        assert!(a.owns(&x));
+        assert!(!b.owns(&x));
+        assert!(!a.owns(&BytesSlice::from_bytes(&[2, 3])));
+    }
+
+    #[test]
+    #[allow(clippy::reversed_empty_ranges)]
+    fn try_slice() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(&[1, 2, 3]);
+        assert_eq!(a.try_slice(1..).unwrap().as_bytes(), &[2, 3]);
+        assert_eq!(a.try_slice(..=2).unwrap().as_bytes(), &[1, 2, 3]);
+        assert!(a.try_slice(3..).unwrap().is_empty());
+        assert!(a.try_slice(..4).is_none());
+        assert!(a.try_slice(2..1).is_none());
+        assert!(a.try_slice(..=usize::MAX).is_none());
+    }
+
+    #[test]
+    #[allow(clippy::reversed_empty_ranges)]
+    fn try_slice_str() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_str("123");
+        a.push(0xff);
+        let b = a.slice(..);
+        assert_eq!(a.try_slice_str(1..3), Ok("23"));
+        assert_eq!(b.try_slice_str(1..3), Ok("23"));
+        assert_eq!(
+            a.try_slice_str(2..1),
+            Err(SliceStrError::Range(RangeError::Inverted {
+                start: 2,
+                end: 1
+            }))
+        );
+        assert_eq!(
+            b.try_slice_str(2..1),
+            Err(SliceStrError::Range(RangeError::Inverted {
+                start: 2,
+                end: 1
+            }))
+        );
+        assert_eq!(
+            a.try_slice_str(..10),
+            Err(SliceStrError::Range(RangeError::OutOfBounds {
+                end: 10,
+                len: 4
+            }))
+        );
+        assert_eq!(
+            b.try_slice_str(1..=usize::MAX),
+            Err(SliceStrError::Range(RangeError::Overflow))
+        );
+        assert!(matches!(a.try_slice_str(..), Err(SliceStrError::Utf8(_))));
+        assert!(matches!(b.try_slice_str(..), Err(SliceStrError::Utf8(_))));
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity overflow")]
+    fn reserve_overflow() {
+        let mut a = AppendOnlyBytes::new();
+        a.push(1);
+        a.reserve(usize::MAX);
+    }
+
+    #[test]
+    fn reserve_large() {
+        let mut a = AppendOnlyBytes::new();
+        a.push(1);
+        a.reserve(1_000_000);
+        assert_eq!(a.capacity(), 1_000_001);
+        a.push(2);
+        assert_eq!(a.capacity(), 1_000_001);
+        a.reserve(1_000_000);
+        assert_eq!(a.capacity(), 2_000_002);
+        assert_eq!(a.as_bytes(), &[1, 2]);
+    }
+
+    #[test]
+    #[cfg(not(feature = "u32_range"))]
+    #[should_panic(expected = "capacity overflow")]
+    fn reserve_doubling_overflow() {
+        let mut a = AppendOnlyBytes::new();
+        a.push(1);
+        // The doubling must not wrap around to a small capacity
+        a.reserve(usize::MAX / 2 + 1);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "reading uninitialized data")]
+    fn read_uninit() {
+        let mut a = AppendOnlyBytes::with_capacity(10);
+        a.push_slice(&[1, 2, 3]);
+        assert_eq!(a.raw.slice_uninit(..).len(), 16);
+        // SAFETY: it will panic before reading the data
+        let _ = unsafe { a.raw.slice(..4) };
+    }
+
+    #[test]
+    fn zero_spare_capacity() {
+        let mut a = AppendOnlyBytes::with_capacity(10);
+        a.push_slice(&[1, 2, 3]);
+        a.zero_spare_capacity();
+        let cap = a.capacity();
+        // SAFETY: the spare capacity is zeroed
+        unsafe { a.set_len(cap) };
+        assert_eq!(&a[..3], &[1, 2, 3]);
+        assert!(a[3..].iter().all(|&x| x == 0));
+    }
+
+    #[test]
+    fn clear() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(&[1, 2, 3]);
+        let cap = a.capacity();
+        let b = a.slice(..);
+        assert!(a.clear().is_err());
+        assert_eq!(a.len(), 3);
+        drop(b);
+        a.clear().unwrap();
+        assert!(a.is_empty());
+        assert_eq!(a.capacity(), cap);
+        a.push(4);
+        assert_eq!(a.as_bytes(), &[4]);
+    }
+
+    #[test]
+    fn to_vec() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(&[1, 2, 3]);
+        let v = a.to_vec();
+        let b = a.to_bytes_slice();
+        let w = b.slice_clone(1..).to_vec();
+        a.push(4);
+        assert_eq!(v, [1, 2, 3]);
+        assert_eq!(w, [2, 3]);
+        assert_eq!(b.as_bytes(), &[1, 2, 3]);
+        assert_eq!(a.to_vec(), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    #[allow(clippy::reversed_empty_ranges)]
+    fn subslice_abs() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(&[0, 1, 2, 3, 4, 5]);
+        let b = a.slice(2..5);
+        assert_eq!(b.subslice_abs(2..5).unwrap().as_bytes(), &[2, 3, 4]);
+        let c = b.subslice_abs(3..4).unwrap();
+        assert_eq!(c.as_bytes(), &[3]);
+        assert_eq!((c.start(), c.end()), (3, 4));
+        assert!(b.subslice_abs(5..5).unwrap().is_empty());
+        assert!(b.subslice_abs(1..3).is_none());
+        assert!(b.subslice_abs(4..6).is_none());
+        assert!(b.subslice_abs(4..3).is_none());
+    }
+
+    #[test]
+    fn merge_all() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(&[0, 1, 2, 3, 4, 5]);
+        let mut slices: Vec<_> = (0..5).map(|i| a.slice(i..i + 1)).collect();
+        BytesSlice::merge_all(&mut slices);
+        assert_eq!(slices.len(), 1);
+        assert_eq!(slices[0].as_bytes(), &[0, 1, 2, 3, 4]);
+
+        let mut slices = vec![
+            a.slice(0..1),
+            a.slice(1..2),
+            a.slice(3..4),
+            BytesSlice::from_bytes(&[4]),
+            a.slice(4..5),
+            a.slice(5..6),
+        ];
+        BytesSlice::merge_all(&mut slices);
+        let slices: Vec<_> = slices.iter().map(|x| x.to_vec()).collect();
+        assert_eq!(slices, vec![vec![0, 1], vec![3], vec![4], vec![4, 5]]);
+
+        let mut slices = Vec::new();
+        BytesSlice::merge_all(&mut slices);
+        assert!(slices.is_empty());
+    }
+
+    #[test]
+    fn merge_before() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(&[0, 1, 2, 3]);
+        let mut x = a.slice(1..2);
+        let y = a.slice(2..3);
+        assert!(x.can_merge(&y));
+        assert!(!x.can_merge_before(&y));
+        x.try_merge(&y).unwrap();
+        assert_eq!(x.as_bytes(), &[1, 2]);
+
+        let mut y = a.slice(2..4);
+        let x = a.slice(0..2);
+        assert!(y.can_merge_before(&x));
+        y.try_merge_before(&x).unwrap();
+        assert_eq!(y.as_bytes(), &[0, 1, 2, 3]);
+
+        let mut z = a.slice(3..4);
+        assert!(z.try_merge_before(&a.slice(0..2)).is_err());
+        assert!(z.try_merge_before(&BytesSlice::from_bytes(&[2])).is_err());
+        assert_eq!(z.as_bytes(), &[3]);
+    }
+
+    #[test]
+    fn merged() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(&[0, 1, 2, 3]);
+        let x = a.slice(..1);
+        let y = a.slice(1..3);
+        let z = x.merged(&y).unwrap();
+        assert_eq!(z.as_bytes(), [x.as_bytes(), y.as_bytes()].concat());
+        assert_eq!(x.as_bytes(), &[0]);
+        assert!(y.merged(&x).is_none());
+        assert!(x.merged(&a.slice(2..)).is_none());
+    }
+
+    #[test]
+    fn index_slice() {
+        let a = BytesSlice::from_bytes(&[0, 1, 2, 3, 4]).slice_clone(1..);
+        assert_eq!(a[0], 1);
+        assert_eq!(a[3], 4);
+        assert_eq!(&a[1..3], &[2, 3]);
+        assert_eq!(&a[..], &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn first_last_get_byte() {
+        let mut a = AppendOnlyBytes::new();
+        assert_eq!(a.first_byte(), None);
+        assert_eq!(a.last_byte(), None);
+        assert_eq!(a.get_byte(0), None);
+        assert_eq!(BytesSlice::empty().first_byte(), None);
+        a.push_slice(&[1, 2, 3]);
+        assert_eq!(a.first_byte(), Some(1));
+        assert_eq!(a.last_byte(), Some(3));
+        assert_eq!(a.get_byte(1), Some(2));
+        assert_eq!(a.get_byte(3), None);
+        let b = a.slice(1..);
+        assert_eq!(b.first_byte(), Some(2));
+        assert_eq!(b.last_byte(), Some(3));
+        assert_eq!(b.get_byte(0), Some(2));
+        assert_eq!(b.get_byte(2), None);
+        // the slice methods are not hidden
+        assert_eq!(b.first(), Some(&2));
+        assert_eq!(b.get(0..2), Some(&[2, 3][..]));
+    }
+
+    #[test]
+    fn starts_ends_with() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_str("GET /index HTTP/1.1\r\n");
+        assert!(a.starts_with(b"GET "));
+        assert!(a.ends_with(b"\r\n"));
+        assert!(!a.starts_with(b"POST"));
+        let b = a.slice(4..10);
+        assert!(b.starts_with(b"/"));
+        assert!(b.ends_with(b"index"));
+        assert!(!b.ends_with(b"HTTP"));
+        assert!(b.starts_with(b""));
+        assert!(b.ends_with(b""));
+        assert!(BytesSlice::empty().starts_with(b""));
+        assert!(!BytesSlice::empty().ends_with(b"a"));
+    }
+
+    #[test]
+    fn find() {
+        let a = BytesSlice::from_bytes(b"key: value\r\n").slice_clone(1..);
+        assert_eq!(a.find_byte(b':'), Some(2));
+        assert_eq!(a.find_byte(b'k'), None);
+        assert_eq!(a.find(b"\r\n"), Some(9));
+        assert_eq!(a.find(b"value"), Some(4));
+        assert_eq!(a.find(b"values"), None);
+        assert_eq!(a.find(b""), Some(0));
+        assert_eq!(BytesSlice::empty().find(b""), Some(0));
+        assert_eq!(BytesSlice::empty().find(b"a"), None);
+        assert_eq!(BytesSlice::empty().find_byte(0), None);
+    }
+
+    #[test]
+    #[cfg(not(feature = "u16_range"))]
+    fn split_on() {
+        let mut a = AppendOnlyBytes::new();
+        for i in 0..(1 << 20) {
+            a.push(if i % 97 == 0 {
+                b'\n'
+            } else {
+                b'a' + (i % 26) as u8
+            });
+        }
+        a.push(b'\n');
+        let b = a.to_bytes_slice();
+        let expected: Vec<&[u8]> = b.as_bytes().split(|&x| x == b'\n').collect();
+        let actual: Vec<BytesSlice> = b.split_on(b'\n').collect();
+        assert_eq!(actual.len(), expected.len());
+        for (x, y) in actual.iter().zip(expected.iter()) {
+            assert!(x.ptr_eq(&b));
+            assert_eq!(x.as_bytes(), *y);
+        }
+        assert_eq!(b.find_byte(b'\n'), Some(0));
+        assert_eq!(b.slice_clone(1..).find_byte(b'\n'), Some(96));
+        assert_eq!(BytesSlice::empty().split_on(b'\n').count(), 1);
+    }
+
+    #[test]
+    fn split_shared() {
+        let a = BytesSlice::from_bytes(b"a,b,,c");
+        let parts: Vec<_> = a.split_shared(b',').collect();
+        assert_eq!(parts.len(), 4);
+        assert_eq!(parts[0].as_bytes(), b"a");
+        assert_eq!(parts[1].as_bytes(), b"b");
+        assert_eq!(parts[2].as_bytes(), b"");
+        assert_eq!(parts[3].as_bytes(), b"c");
+        assert!(parts.iter().all(|x| x.ptr_eq(&a)));
+        let parts: Vec<_> = a.slice_clone(..4).split_shared(b',').collect();
+        assert_eq!(parts.len(), 3);
+        assert!(parts[2].is_empty());
+        let parts: Vec<_> = a.slice_clone(1..).split_shared(b',').collect();
+        assert_eq!(parts.len(), 4);
+        assert!(parts[0].is_empty());
+        assert_eq!(parts[3].as_bytes(), b"c");
+        // the slice method is not hidden
+        assert_eq!(a.split(|&b| b == b',').count(), 4);
+    }
+
+    #[test]
+    fn lines() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_str("a\nb\r\n\nc");
+        let lines: Vec<_> = a.lines().map(|x| x.unwrap()).collect();
+        assert_eq!(lines, vec!["a", "b", "", "c"]);
+        a.push_str("\n");
+        assert_eq!(a.lines().count(), 4);
+        a.push(0xff);
+        let lines: Vec<_> = a.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[4].is_err());
+        assert_eq!(AppendOnlyBytes::new().lines().count(), 0);
+    }
 
-    use super::*;
     #[test]
-    fn test() {
+    fn chunks() {
+        let a = BytesSlice::from_bytes(&[0, 1, 2, 3, 4, 5, 6]).slice_clone(1..);
+        let chunks: Vec<_> = a.chunks_shared(4).collect();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].as_bytes(), &[1, 2, 3, 4]);
+        assert_eq!(chunks[1].as_bytes(), &[5, 6]);
+        assert!(chunks.iter().all(|x| x.ptr_eq(&a)));
+        assert!(chunks[0].can_merge(&chunks[1]));
+        assert_eq!(a.chunks_shared(3).count(), 2);
+        assert_eq!(BytesSlice::empty().chunks_shared(3).count(), 0);
+        let chunks: Vec<&[u8]> = a.chunks(4).collect();
+        assert_eq!(chunks.len(), 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn chunks_zero() {
+        BytesSlice::from_bytes(&[1]).chunks_shared(0);
+    }
+
+    #[test]
+    fn windows() {
+        let a = BytesSlice::from_bytes(b"abcd");
+        let windows: Vec<_> = a.windows_shared(2).collect();
+        assert_eq!(windows.len(), 3);
+        assert_eq!(windows[0].as_bytes(), b"ab");
+        assert_eq!(windows[1].as_bytes(), b"bc");
+        assert_eq!(windows[2].as_bytes(), b"cd");
+        assert!(windows.iter().all(|x| x.ptr_eq(&a)));
+        assert_eq!(a.windows_shared(4).count(), 1);
+        assert_eq!(a.windows_shared(5).count(), 0);
+        let windows: Vec<&[u8]> = a.windows(2).collect();
+        assert_eq!(windows[1], b"bc");
+    }
+
+    #[test]
+    #[should_panic]
+    fn windows_zero() {
+        BytesSlice::from_bytes(&[1]).windows_shared(0);
+    }
+
+    #[test]
+    fn push_slice_ret() {
+        let mut a = AppendOnlyBytes::with_capacity(10);
+        a.push_slice(b"12");
+        let b = a.push_slice_ret(b"345");
+        assert_eq!(b.as_bytes(), b"345");
+        assert_eq!((b.start(), b.end()), (2, 5));
+        let c = a.push_slice_ret(b"6");
+        assert!(b.ptr_eq(&a.slice(1..4)));
+        assert!(b.can_merge(&c));
+    }
+
+    #[test]
+    fn push_bytes_slice() {
+        let mut a = AppendOnlyBytes::with_capacity(4);
+        a.push_slice(b"12");
+        let b = a.slice(..);
+        a.push_bytes_slice(&b);
+        assert_eq!(a.as_bytes(), b"1212");
+        // reallocates while copying from the same buffer
+        let b = a.slice(1..);
+        a.push_bytes_slice(&b);
+        assert_eq!(a.as_bytes(), b"1212212");
+        a.push_bytes_slice(&BytesSlice::from_bytes(b"3"));
+        assert_eq!(a.as_bytes(), b"12122123");
+
+        // a slice at the tail is copied too
+        let mut a = AppendOnlyBytes::with_capacity(16);
+        a.push_slice(b"ab");
+        let b = a.slice(..);
+        assert!(a.is_contiguous_with(&b));
+        a.push_bytes_slice(&b);
+        assert_eq!(a.as_bytes(), b"abab");
+        assert!(a.owns(&b));
+        assert_eq!(b.as_bytes(), b"ab");
+    }
+
+    #[test]
+    fn push_within_capacity() {
         let mut a = AppendOnlyBytes::new();
-        let mut count = 0;
-        for _ in 0..100 {
-            a.push(8);
-            count += 1;
-            assert_eq!(a.len(), count);
+        a.reserve_exact(3);
+        for i in 0..3 {
+            a.push_within_capacity(i).unwrap();
         }
+        assert_eq!(a.push_within_capacity(3), Err(3));
+        assert_eq!(a.capacity(), 3);
+        assert_eq!(a.as_bytes(), &[0, 1, 2]);
+    }
 
-        for _ in 0..100 {
-            a.push_slice(&[1, 2]);
-            count += 2;
-            assert_eq!(a.len(), count);
+    #[test]
+    fn push_slice_tracked() {
+        let mut a = AppendOnlyBytes::new();
+        a.reserve_exact(4);
+        assert!(!a.push_slice_tracked(b"12"));
+        assert!(!a.push_slice_tracked(b"34"));
+        let b = a.slice(..);
+        assert!(a.push_slice_tracked(b"5"));
+        assert!(!a.owns(&b));
+        assert!(!a.push_slice_tracked(b"6"));
+        assert_eq!(a.as_bytes(), b"123456");
+
+        // unique, so it may grow in place
+        let mut a = AppendOnlyBytes::new();
+        a.reserve_exact(1);
+        for i in 0..32 {
+            let n = 1 << (i % 8);
+            let ptr = a.as_ptr();
+            let will_realloc = a.will_realloc(n);
+            let moved = a.push_slice_tracked(&[0; 128][..n]);
+            assert_eq!(moved, ptr != a.as_ptr());
+            assert!(will_realloc || !moved);
         }
     }
 
     #[test]
-    fn it_works() {
+    fn as_ptr() {
         let mut a = AppendOnlyBytes::new();
-        a.push_str("123");
-        assert_eq!(a.slice_str(0..1).unwrap(), "1");
-        let b = a.slice(..);
-        for _ in 0..10 {
-            a.push_str("456");
-            dbg!(a.slice_str(..).unwrap());
+        a.push_str("hello");
+        let b = a.slice(1..4);
+        // SAFETY: the pointers point to the initialized bytes of `a` and `b`
+        let (x, y) = unsafe {
+            (
+                core::slice::from_raw_parts(a.as_ptr(), a.len()),
+                core::slice::from_raw_parts(b.as_ptr(), b.len()),
+            )
+        };
+        assert_eq!(x, b"hello");
+        assert_eq!(y, b"ell");
+        // SAFETY: the bytes are valid UTF-8
+        unsafe {
+            assert_eq!(a.as_str_unchecked(), "hello");
+            assert_eq!(b.as_str_unchecked(), "ell");
         }
-        let c = a.slice(..);
-        drop(a);
-        dbg!(c.slice_str(..).unwrap());
-        assert_eq!(c.len(), 33);
-        assert_eq!(c.slice_str(..6).unwrap(), "123456");
+    }
 
-        assert_eq!(b.deref(), "123".as_bytes());
+    #[test]
+    fn clone_as_slice() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(&[1, 2, 3]);
+        let b = a.clone_as_slice();
+        assert!(a.owns(&b));
+        assert!(!a.clone().owns(&b));
+        assert_eq!(a, b);
     }
 
     #[test]
-    fn push_large() {
+    fn clone_capacity() {
+        let mut a = AppendOnlyBytes::with_capacity(1 << 20);
+        a.push_slice(&[1; 10]);
+        let mut b = a.clone();
+        assert_eq!(b.capacity(), 10);
+        assert_eq!(a, b);
+        b.push(2);
+        assert_eq!(b.len(), 11);
+        let c = AppendOnlyBytes::fixed_with_capacity(8).clone();
+        assert_eq!(c.capacity(), 8);
+    }
+
+    #[test]
+    fn freeze() {
         let mut a = AppendOnlyBytes::new();
-        a.push_slice(&[1; 10000]);
-        assert_eq!(a.as_bytes(), &[1; 10000]);
+        a.push_slice(&[1, 2, 3]);
+        let b = a.clone();
+        let c = a.freeze();
+        assert_eq!(b, c);
+        assert_eq!((c.start(), c.end()), (0, 3));
     }
 
     #[test]
-    fn threads() {
+    fn append() {
         let mut a = AppendOnlyBytes::new();
-        a.push_str("123");
-        assert_eq!(a.slice_str(0..1).unwrap(), "1");
-        let (tx, rx): (Sender<AppendOnlyBytes>, Receiver<AppendOnlyBytes>) = mpsc::channel();
+        a.extend_from_slice(b"12");
+        let mut b = AppendOnlyBytes::new();
+        b.push_slice(b"34");
+        a.append(&mut b);
+        assert_eq!(a.as_bytes(), b"1234");
+        assert!(b.is_empty());
+
+        b.push_slice(b"56");
+        let c = b.slice(..);
+        a.append(&mut b);
+        assert_eq!(a.as_bytes(), b"123456");
+        assert_eq!(b.as_bytes(), b"56");
+        assert_eq!(c, b"56".as_slice());
+    }
+
+    #[test]
+    fn repeated() {
+        let mut a = AppendOnlyBytes::from_repeated(0xAB, 1000);
+        assert_eq!(a.as_bytes(), vec![0xAB; 1000].as_slice());
+        assert_eq!(a.capacity(), 1000);
+        a.push_repeated(1, 3);
+        a.push_repeated(2, 0);
+        assert_eq!(&a[998..], &[0xAB, 0xAB, 1, 1, 1]);
+        assert!(AppendOnlyBytes::from_repeated(0, 0).is_empty());
+    }
+
+    #[test]
+    fn with_capacity_aligned() {
+        for align in [1, 2, 64, 4096] {
+            let mut a = AppendOnlyBytes::with_capacity_aligned(10, align);
+            assert_eq!(a.as_ptr() as usize % align, 0);
+            assert_eq!(a.capacity(), 10);
+            a.push_slice(&[1; 100]);
+            assert_eq!(a.as_ptr() as usize % align, 0);
+            let b = a.clone();
+            assert_eq!(b.as_ptr() as usize % align, 0);
+            assert_eq!(a.into_vec().unwrap(), vec![1; 100]);
+            assert_eq!(b.to_slice(), [1; 100].as_slice());
+            let empty = AppendOnlyBytes::with_capacity_aligned(0, align);
+            assert_eq!(empty.as_ptr() as usize % align, 0);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn with_capacity_aligned_invalid() {
+        AppendOnlyBytes::with_capacity_aligned(10, 3);
+    }
+
+    #[test]
+    fn is_contiguous_with() {
+        let mut a = AppendOnlyBytes::with_capacity(4);
+        a.push_slice(b"12");
         let b = a.slice(..);
-        let t = thread::spawn(move || {
-            for _ in 0..10 {
-                a.push_str("456");
-                dbg!(a.slice_str(..).unwrap());
+        let c = a.slice(..1);
+        assert!(a.is_contiguous_with(&b));
+        assert!(!a.is_contiguous_with(&c));
+        a.push_slice(b"3");
+        assert!(!a.is_contiguous_with(&b));
+        let mut d = a.slice(..);
+        assert!(a.is_contiguous_with(&d));
+        a.push_slice(b"4");
+        d.try_merge(&a.slice(3..)).unwrap();
+        assert!(a.is_contiguous_with(&d));
+
+        // reallocate
+        a.push_slice(b"5");
+        assert!(!a.is_contiguous_with(&d));
+        assert_eq!(d, b"1234".as_slice());
+        assert!(a.is_contiguous_with(&a.slice(..)));
+    }
+
+    #[test]
+    fn growth_policy() {
+        let mut a = AppendOnlyBytes::new();
+        a.set_growth_policy(GrowthPolicy {
+            min_capacity: 64,
+            growth_factor: 1.5,
+        });
+        let mut capacities = vec![];
+        for _ in 0..300 {
+            a.push(1);
+            if capacities.last() != Some(&a.capacity()) {
+                capacities.push(a.capacity());
             }
-            let c = a.slice(..);
-            tx.send(a).unwrap();
-            dbg!(c.slice_str(..).unwrap());
-            assert_eq!(c.len(), 33);
-            assert_eq!(c.slice_str(..6).unwrap(), "123456");
+        }
+        assert_eq!(capacities, vec![64, 96, 144, 216, 324]);
+        assert_eq!(a.clone().growth_policy(), a.growth_policy());
+
+        a.reserve(1000);
+        assert_eq!(a.capacity(), 1300);
+    }
+
+    #[test]
+    #[should_panic]
+    fn growth_policy_invalid() {
+        AppendOnlyBytes::new().set_growth_policy(GrowthPolicy {
+            min_capacity: 0,
+            growth_factor: 1.0,
         });
-        let t1 = thread::spawn(move || {
-            assert_eq!(b.deref(), "123".as_bytes());
-            for _ in 0..10 {
-                let c = b.slice_clone(0..1);
-                assert_eq!(c.deref(), "1".as_bytes());
+    }
+
+    #[test]
+    fn slice_count() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(b"123");
+        assert!(!a.is_shared());
+        assert_eq!(a.slice_count(), 0);
+        let b = a.slice(..1);
+        let c = a.slice(1..);
+        assert!(a.is_shared());
+        assert_eq!(a.slice_count(), 2);
+        assert_eq!(b.slice_count(), 2);
+        drop(c);
+        assert_eq!(a.slice_count(), 1);
+        assert!(b.is_shared());
+        drop(a);
+        assert!(!b.is_shared());
+        assert_eq!(b.slice_count(), 0);
+    }
+
+    #[test]
+    fn from_conversions() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(b"123");
+        let ptr = a.as_ptr();
+        let b: BytesSlice = a.into();
+        assert_eq!(b, b"123".as_slice());
+        assert_eq!(b.as_ptr(), ptr);
+
+        let c: BytesSlice = b"456".as_slice().into();
+        assert_eq!(c, b"456".as_slice());
+
+        let v = vec![7, 8, 9];
+        let ptr = v.as_ptr();
+        let d = BytesSlice::from(v);
+        assert_eq!(d, [7, 8, 9].as_slice());
+        assert_eq!(d.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn as_str() {
+        let valid = BytesSlice::from_bytes("你好".as_bytes());
+        assert_eq!(valid.as_str().unwrap(), "你好");
+        assert_eq!(String::try_from(valid).unwrap(), "你好");
+
+        let invalid = BytesSlice::from_bytes("你好".as_bytes()).slice_clone(1..);
+        assert!(invalid.as_str().is_err());
+        let err = String::try_from(invalid).unwrap_err();
+        assert_eq!(err.into_bytes(), &"你好".as_bytes()[1..]);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn buf_read() {
+        use std::io::BufRead;
+        let mut a = BytesSlice::from_bytes("你好\nworld\n!".as_bytes());
+        let mut line = String::new();
+        a.read_line(&mut line).unwrap();
+        assert_eq!(line, "你好\n");
+        assert_eq!(a, b"world\n!".as_slice());
+        let rest: Vec<String> = a.lines().map(|x| x.unwrap()).collect();
+        assert_eq!(rest, vec!["world", "!"]);
+    }
+
+    #[test]
+    fn copy_from_offset() {
+        let a = BytesSlice::from_bytes(b"12345");
+        let mut buf = [0; 3];
+        assert_eq!(a.copy_from_offset(1, &mut buf), 3);
+        assert_eq!(&buf, b"234");
+        let mut buf = [0; 3];
+        assert_eq!(a.copy_from_offset(3, &mut buf), 2);
+        assert_eq!(&buf, b"45\0");
+        assert_eq!(a.copy_from_offset(5, &mut buf), 0);
+        assert_eq!(a.copy_from_offset(100, &mut buf), 0);
+        assert_eq!(a.copy_from_offset(0, &mut []), 0);
+    }
+
+    #[test]
+    fn reset_for_reuse() {
+        let mut a = AppendOnlyBytes::with_capacity(64);
+        a.push_slice(b"123");
+        let ptr = a.as_ptr();
+        let a = a.reset_for_reuse().unwrap();
+        assert!(a.is_empty());
+        assert_eq!(a.capacity(), 64);
+        assert_eq!(a.as_ptr(), ptr);
+
+        let mut a = a;
+        a.push_slice(b"456");
+        let b = a.slice(..);
+        assert!(a.reset_for_reuse().is_none());
+        assert_eq!(b, b"456".as_slice());
+    }
+
+    #[test]
+    fn to_arc_slice() {
+        let a = BytesSlice::from_bytes(b"12345").slice_clone(1..4);
+        let arc = a.to_arc_slice();
+        assert_eq!(&*arc, a.as_bytes());
+        assert_ne!(arc.as_ptr(), a.as_ptr());
+    }
+
+    #[test]
+    fn with_capacity_rounding() {
+        assert_eq!(AppendOnlyBytes::with_capacity(0).capacity(), 0);
+        assert_eq!(AppendOnlyBytes::with_capacity(1).capacity(), 16);
+        assert_eq!(AppendOnlyBytes::with_capacity(32).capacity(), 32);
+        assert_eq!(AppendOnlyBytes::with_capacity(33).capacity(), 48);
+        assert_eq!(AppendOnlyBytes::fixed_with_capacity(33).capacity(), 33);
+        let mut a = AppendOnlyBytes::with_exact_capacity(33);
+        assert_eq!(a.capacity(), 33);
+        assert!(!a.is_fixed());
+        a.push_slice(&[0; 34]);
+        assert!(a.capacity() >= 34);
+    }
+
+    #[test]
+    fn push_large_slice() {
+        let data = vec![7; 10 << 20];
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(b"1234");
+        a.push_large_slice(&data);
+        assert_eq!(a.capacity(), 4 + (10 << 20));
+        assert_eq!(&a[..5], &[b'1', b'2', b'3', b'4', 7]);
+        assert_eq!(a.len(), 4 + (10 << 20));
+    }
+
+    #[test]
+    #[cfg(feature = "allocator_api")]
+    fn allocator_api() {
+        use core::{
+            alloc::{AllocError, Allocator, Layout},
+            ptr::NonNull,
+            sync::atomic::{AtomicUsize, Ordering},
+        };
+        use std::alloc::Global;
+
+        #[derive(Default)]
+        struct Counting {
+            allocated: AtomicUsize,
+            freed: AtomicUsize,
+        }
+
+        #[derive(Clone)]
+        struct CountingAllocator(Arc<Counting>);
+
+        // SAFETY: it forwards to `Global`
+        unsafe impl Allocator for CountingAllocator {
+            fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+                self.0.allocated.fetch_add(1, Ordering::Relaxed);
+                Global.allocate(layout)
+            }
+
+            unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                self.0.freed.fetch_add(1, Ordering::Relaxed);
+                // SAFETY: it's allocated by `Global` with the same layout
+                unsafe { Global.deallocate(ptr, layout) }
+            }
+        }
+
+        let counting = Arc::new(Counting::default());
+        let allocator = CountingAllocator(counting.clone());
+        let mut a = AppendOnlyBytes::with_capacity_in(4, allocator.clone());
+        a.push_slice(b"1234");
+        let b = a.slice(..);
+        // reallocate
+        a.push_slice(b"5");
+        let c = a.clone();
+        assert_eq!(counting.allocated.load(Ordering::Relaxed), 3);
+        assert_eq!(counting.freed.load(Ordering::Relaxed), 0);
+        drop(b);
+        assert_eq!(counting.freed.load(Ordering::Relaxed), 1);
+        assert_eq!(a.into_vec().unwrap(), b"12345");
+        assert_eq!(c, b"12345".as_slice());
+        drop(c);
+        assert_eq!(counting.freed.load(Ordering::Relaxed), 3);
+
+        let a = AppendOnlyBytes::new_in(allocator);
+        assert!(a.is_empty());
+        drop(a);
+        assert_eq!(
+            counting.allocated.load(Ordering::Relaxed),
+            counting.freed.load(Ordering::Relaxed)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "allocator_api")]
+    fn allocator_api_static_arena() {
+        use core::{
+            alloc::{AllocError, Allocator, Layout},
+            ptr::NonNull,
+            sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
+        };
+
+        /// A bump arena that never frees
+        struct Bump {
+            start: AtomicPtr<u8>,
+            capacity: usize,
+            used: AtomicUsize,
+        }
+
+        // SAFETY: the returned memory is inside the leaked buffer and never handed out twice
+        unsafe impl Allocator for Bump {
+            fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+                let start = self.start.load(Ordering::Relaxed);
+                let offset = |used: usize| {
+                    (start as usize + used).next_multiple_of(layout.align()) - start as usize
+                };
+                let used = self
+                    .used
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                        let end = offset(used) + layout.size();
+                        (end <= self.capacity).then_some(end)
+                    })
+                    .map_err(|_| AllocError)?;
+                let ptr = NonNull::new(start.wrapping_add(offset(used))).ok_or(AllocError)?;
+                Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
             }
+
+            unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {}
+        }
+
+        let memory: &'static mut [u8] = Vec::leak(vec![0; 256]);
+        let arena: &'static Bump = Box::leak(Box::new(Bump {
+            start: AtomicPtr::new(memory.as_mut_ptr()),
+            capacity: memory.len(),
+            used: AtomicUsize::new(0),
+        }));
+        let start = arena.start.load(Ordering::Relaxed) as usize;
+        let in_arena = |a: &AppendOnlyBytes| (start..start + 256).contains(&(a.as_ptr() as usize));
+
+        let mut a = AppendOnlyBytes::with_capacity_in(16, arena);
+        a.push_slice(&[1; 16]);
+        let b = a.slice(..);
+        // reallocate inside the arena
+        a.push_slice(&[2; 16]);
+        assert!(!b.ptr_eq(&a.slice(..)));
+        assert!(in_arena(&a));
+        assert_eq!(&a[..17], &[[1; 16].as_slice(), &[2]].concat());
+        let c = std::thread::spawn(move || b).join().unwrap();
+        assert_eq!(c, [1; 16].as_slice());
+        assert!(arena.used.load(Ordering::Relaxed) >= 48);
+    }
+
+    #[test]
+    fn split_off() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(b"header:body");
+        let (mut head, tail) = a.split_off(7);
+        assert_eq!(head, b"header:".as_slice());
+        assert_eq!(tail, b"body".as_slice());
+        assert!(head.ptr_eq(&tail));
+        head.try_merge(&tail).unwrap();
+        assert_eq!(head, a);
+
+        let (head, tail) = a.split_off(a.len());
+        assert_eq!(head, a);
+        assert!(tail.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn split_off_out_of_bounds() {
+        let _ = AppendOnlyBytes::new().split_off(1);
+    }
+
+    #[test]
+    fn runs() {
+        let a = BytesSlice::from_bytes(b"aaabbc");
+        let runs: Vec<BytesSlice> = a.runs().collect();
+        assert_eq!(
+            runs.iter().map(|x| x.len()).collect::<Vec<_>>(),
+            vec![3, 2, 1]
+        );
+        assert_eq!(runs[1], b"bb".as_slice());
+        assert!(runs.iter().all(|x| x.ptr_eq(&a)));
+        assert_eq!(BytesSlice::empty().runs().count(), 0);
+    }
+
+    #[test]
+    fn count_byte() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_str("a,b,,c\n");
+        assert!(a.contains_byte(b','));
+        assert!(!a.contains_byte(b'd'));
+        assert_eq!(a.count_byte(b','), 3);
+        assert_eq!(a.count_byte(b'\n'), 1);
+        let b = a.slice(2..);
+        assert!(b.contains_byte(b'c'));
+        assert!(!b.contains_byte(b'a'));
+        assert_eq!(b.count_byte(b','), 2);
+        assert_eq!(b.count_byte(b'x'), 0);
+    }
+
+    #[test]
+    fn eq_ignore_ascii_case() {
+        let a = BytesSlice::from_bytes(b"Content-Type");
+        assert!(a.eq_ignore_ascii_case(b"content-type"));
+        assert!(a.eq_ignore_ascii_case(b"CONTENT-TYPE"));
+        assert!(!a.eq_ignore_ascii_case(b"content-length"));
+        let mut out = AppendOnlyBytes::new();
+        out.push_slice(b"> ");
+        a.to_ascii_lowercase_into(&mut out);
+        assert_eq!(out.as_bytes(), b"> content-type");
+    }
+
+    #[test]
+    fn trim_ascii_shared() {
+        let a = BytesSlice::from_bytes(b" \t abc d\r\n");
+        assert_eq!(a.trim_ascii_shared(), b"abc d".as_slice());
+        assert_eq!(a.trim_ascii_start_shared(), b"abc d\r\n".as_slice());
+        assert_eq!(a.trim_ascii_end_shared(), b" \t abc d".as_slice());
+        assert!(a.trim_ascii_shared().ptr_eq(&a));
+        assert_eq!(a.trim_ascii_shared().start(), 3);
+
+        let b = BytesSlice::from_bytes(b"abc");
+        assert_eq!(b.trim_ascii_shared(), b);
+        let c = BytesSlice::from_bytes(b" \n ");
+        assert!(c.trim_ascii_shared().is_empty());
+        assert!(c.trim_ascii_start_shared().is_empty());
+        assert!(c.trim_ascii_end_shared().is_empty());
+        let trimmed: &[u8] = a.trim_ascii();
+        assert_eq!(trimmed, b"abc d");
+    }
+
+    #[test]
+    fn append_with() {
+        let mut a = AppendOnlyBytes::new();
+        a.push(0);
+        a.append_with(10, |buf| {
+            assert_eq!(buf, &[0; 10]);
+            buf[..3].copy_from_slice(&[1, 2, 3]);
+            3
         });
+        assert_eq!(a.len(), 4);
+        assert!(a.capacity() >= 11);
+        assert_eq!(a.as_bytes(), &[0, 1, 2, 3]);
+        a.append_with(2, |_| 0);
+        assert_eq!(a.len(), 4);
+    }
 
-        let a = rx.recv().unwrap();
-        assert_eq!(a.len(), 33);
-        assert_eq!(&a[..6], "123456".as_bytes());
-        t.join().unwrap();
-        t1.join().unwrap()
+    #[test]
+    #[should_panic(expected = "initialized more than reserved")]
+    fn append_with_too_many() {
+        let mut a = AppendOnlyBytes::new();
+        a.append_with(2, |_| 3);
     }
 
     #[test]
-    fn from_bytes() {
-        let a = BytesSlice::from_bytes(b"123");
-        assert_eq!(a.len(), 3);
-        assert_eq!(a.slice_str(..).unwrap(), "123");
+    fn append_with_unchecked() {
+        let mut a = AppendOnlyBytes::new();
+        a.push(0);
+        // SAFETY: the first 3 bytes are initialized
+        unsafe {
+            a.append_with_unchecked(10, |spare| {
+                assert_eq!(spare.len(), 10);
+                for (i, x) in spare[..3].iter_mut().enumerate() {
+                    x.write(i as u8 + 1);
+                }
+                3
+            });
+        }
+        assert_eq!(a.len(), 4);
+        assert!(a.capacity() >= 11);
+        assert_eq!(a.as_bytes(), &[0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn grow_in_place() {
+        let mut a = AppendOnlyBytes::new();
+        for i in 0..100u8 {
+            a.push_slice(&[i; 100]);
+        }
+        assert_eq!(a.len(), 10000);
+        for i in 0..100 {
+            assert!(a[i * 100..(i + 1) * 100].iter().all(|&x| x == i as u8));
+        }
+
+        // shared buffers are never moved
+        let b = a.slice(..10);
+        let ptr = b.as_ptr();
+        a.reserve(a.capacity());
+        assert!(!a.owns(&b));
+        assert_eq!(b.as_ptr(), ptr);
+        assert_eq!(a.as_bytes()[..10], *b);
+    }
+
+    #[test]
+    fn eq_str() {
+        let a = BytesSlice::from_bytes(b"hello world").slice_clone(..5);
+        assert_eq!(a, "hello");
+        assert_eq!(*"hello", a);
+        assert_eq!("hello", a);
+        assert_ne!(a, "hello world");
+        assert_ne!(a, "Hello");
+        assert!(a != *"");
+    }
+
+    #[test]
+    fn max_capacity() {
+        let mut a = AppendOnlyBytes::with_max_capacity(100);
+        assert_eq!(a.max_capacity(), 100);
+        for _ in 0..9 {
+            a.try_extend_from_slice(&[1; 10]).unwrap();
+        }
+        assert!(a.capacity() <= 100);
+        assert!(a.try_reserve(11).is_err());
+        assert!(a.try_extend_from_slice(&[2; 11]).is_err());
+        assert!(a.try_reserve(usize::MAX).is_err());
+        assert_eq!(a.len(), 90);
+        assert!(a.capacity() <= 100);
+        a.try_extend_from_slice(&[2; 10]).unwrap();
+        assert_eq!(a.capacity(), 100);
+        assert!(a.try_reserve(1).is_err());
+        assert_eq!(a.clone().max_capacity(), 100);
+
+        let mut b = AppendOnlyBytes::fixed_with_capacity(4);
+        b.try_extend_from_slice(b"1234").unwrap();
+        assert!(b.try_extend_from_slice(b"5").is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity limit exceeded")]
+    fn max_capacity_exceeded() {
+        let mut a = AppendOnlyBytes::with_max_capacity(10);
+        a.push_slice(&[1; 11]);
+    }
+
+    #[test]
+    fn join() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(b"1234");
+        let b = a.slice(..2);
+        let c = a.slice(2..);
+        let joined = BytesSlice::join(&b, &c).unwrap();
+        assert_eq!(joined, b"1234".as_slice());
+        assert!(joined.ptr_eq(&b));
+        assert_eq!(b, b"12".as_slice());
+        assert!(BytesSlice::join(&c, &b).is_err());
+
+        let d = BytesSlice::from_bytes(b"34");
+        assert!(BytesSlice::join(&b, &d).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "u32_range")]
+    #[should_panic(expected = "offset out of range")]
+    fn u32_range_overflow() {
+        let _ = BytesSlice::new(empty_raw(), 0, u32::MAX as usize + 1);
+    }
+
+    #[test]
+    #[cfg(feature = "u32_range")]
+    fn u32_range_limit() {
+        let raw = empty_raw();
+        let a = BytesSlice::new(raw, u32::MAX as usize, u32::MAX as usize);
+        assert_eq!(a.start(), u32::MAX as usize);
+
+        let mut b = AppendOnlyBytes::new();
+        b.push(1);
+        assert_eq!(b.max_capacity(), u32::MAX as usize);
+        assert!(b.try_reserve(u32::MAX as usize).is_err());
+        assert_eq!(b.capacity(), 32);
+        assert_eq!(
+            AppendOnlyBytes::with_max_capacity(usize::MAX).max_capacity(),
+            u32::MAX as usize
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "u32_range")]
+    #[should_panic(expected = "capacity limit exceeded")]
+    fn u32_range_growth() {
+        let mut b = AppendOnlyBytes::new();
+        b.push(1);
+        b.reserve(u32::MAX as usize);
+    }
+
+    #[test]
+    #[cfg(all(feature = "u16_range", not(feature = "u32_range")))]
+    fn u16_range() {
+        use core::mem::size_of;
+
+        /// The layout of `BytesSlice` under `u32_range`
+        #[allow(dead_code)]
+        struct U32Slice(PackedArc, u32, u32);
+        assert!(size_of::<BytesSlice>() < size_of::<U32Slice>());
+
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(&[1; 1000]);
+        a.push_slice(b"hello");
+        let b = a.slice(1000..);
+        assert_eq!((b.start(), b.end()), (1000, 1005));
+        assert_eq!(b, "hello");
+        assert_eq!(b.slice_clone(1..3), "el");
+
+        a.push_repeated(0, u16::MAX as usize - a.len());
+        assert_eq!(a.slice(..).end(), u16::MAX as usize);
+        // the buffer can still grow, only the slices must stay in range
+        assert_eq!(a.max_capacity(), usize::MAX);
+        a.push(1);
+        assert_eq!(a.len(), u16::MAX as usize + 1);
+        assert_eq!(a.as_bytes()[u16::MAX as usize], 1);
+        let mut a = AppendOnlyBytes::with_max_capacity(u16::MAX as usize);
+        assert!(a.try_reserve(u16::MAX as usize + 1).is_err());
+    }
+
+    #[test]
+    #[cfg(all(feature = "u16_range", not(feature = "u32_range")))]
+    #[should_panic(expected = "offset out of range")]
+    fn u16_range_overflow() {
+        let _ = BytesSlice::new(empty_raw(), 0, u16::MAX as usize + 1);
     }
 }
@@ -1,18 +1,69 @@
-use std::{mem::ManuallyDrop, ops::RangeBounds};
+#[cfg(feature = "allocator_api")]
+use alloc::alloc::{AllocError, Allocator};
+use alloc::sync::Arc;
+use alloc::{
+    alloc::{alloc, dealloc, handle_alloc_error, Layout},
+    vec::Vec,
+};
+#[cfg(feature = "allocator_api")]
+use core::ptr::NonNull;
+#[cfg(debug_assertions)]
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::{
+    mem::{ManuallyDrop, MaybeUninit},
+    ops::{Deref, RangeBounds},
+};
 
 use crate::get_range;
 
-/// In this struct, `ptr` and `capacity` cannot be changed after created
+/// In this struct, `ptr` and `capacity` cannot be changed after created, unless it's uniquely owned
+/// (see [`RawBytes::grow_in_place`])
 pub(crate) struct RawBytes {
     ptr: *mut u8,
     capacity: usize,
+    /// The alignment of the allocation. If it's 1, the memory is allocated by `Vec<u8>`.
+    align: usize,
+    /// The allocator of the memory, if it's not allocated by the global allocator.
+    #[cfg(feature = "allocator_api")]
+    allocator: Option<SharedAllocator>,
+    /// The length of the initialized prefix. It's only tracked in debug builds.
+    #[cfg(debug_assertions)]
+    initialized: AtomicUsize,
 }
 
+/// A type-erased allocator shared by the reallocated buffers, so they are all allocated by it.
+#[cfg(feature = "allocator_api")]
+pub(crate) type SharedAllocator = Arc<dyn Allocator + Send + Sync>;
+
 impl Drop for RawBytes {
     fn drop(&mut self) {
-        // SAFETY: We are the only owner of this memory
-        unsafe {
-            Vec::from_raw_parts(self.ptr, 0, self.capacity);
+        #[cfg(feature = "allocator_api")]
+        if let Some(allocator) = &self.allocator {
+            // SAFETY: We are the only owner of this memory, and it's allocated by `allocator`
+            // with the same layout in `with_capacity_in`
+            unsafe {
+                allocator.deallocate(
+                    NonNull::new_unchecked(self.ptr),
+                    Layout::from_size_align_unchecked(self.capacity, self.align),
+                );
+            }
+            return;
+        }
+
+        if self.align == 1 {
+            // SAFETY: We are the only owner of this memory, and it's allocated by `Vec<u8>`
+            unsafe {
+                Vec::from_raw_parts(self.ptr, 0, self.capacity);
+            }
+        } else if self.capacity != 0 {
+            // SAFETY: We are the only owner of this memory, and it's allocated by `alloc` with
+            // the same layout in `with_capacity_aligned`
+            unsafe {
+                dealloc(
+                    self.ptr,
+                    Layout::from_size_align_unchecked(self.capacity, self.align),
+                );
+            }
         }
     }
 }
@@ -24,15 +75,148 @@ impl RawBytes {
         vec.into()
     }
 
+    /// Allocate `capacity` bytes aligned to `align`.
+    ///
+    /// # Panics
+    ///
+    /// If `align` is not a power of two, or the rounded up capacity overflows `isize`.
+    pub fn with_capacity_aligned(capacity: usize, align: usize) -> Self {
+        if align == 1 {
+            return Self::with_capacity(capacity);
+        }
+
+        let layout = Layout::from_size_align(capacity, align).expect("invalid alignment");
+        let ptr = if capacity == 0 {
+            core::ptr::null_mut::<u8>().wrapping_add(align)
+        } else {
+            // SAFETY: the size of the layout is not zero
+            let ptr = unsafe { alloc(layout) };
+            if ptr.is_null() {
+                handle_alloc_error(layout);
+            }
+            ptr
+        };
+
+        Self {
+            ptr,
+            capacity,
+            align,
+            #[cfg(feature = "allocator_api")]
+            allocator: None,
+            #[cfg(debug_assertions)]
+            initialized: AtomicUsize::new(0),
+        }
+    }
+
+    /// Allocate `capacity` bytes aligned to `align` with `allocator`.
+    #[cfg(feature = "allocator_api")]
+    pub fn with_capacity_in(capacity: usize, align: usize, allocator: SharedAllocator) -> Self {
+        let layout = Layout::from_size_align(capacity, align).expect("invalid alignment");
+        let ptr = match allocator.allocate(layout) {
+            Ok(ptr) => ptr.as_ptr() as *mut u8,
+            Err(AllocError) => handle_alloc_error(layout),
+        };
+
+        Self {
+            ptr,
+            capacity,
+            align,
+            allocator: Some(allocator),
+            #[cfg(debug_assertions)]
+            initialized: AtomicUsize::new(0),
+        }
+    }
+
+    /// Allocate a new buffer of `capacity` bytes with the same alignment and allocator.
+    pub fn with_capacity_like(&self, capacity: usize) -> Self {
+        #[cfg(feature = "allocator_api")]
+        if let Some(allocator) = &self.allocator {
+            return Self::with_capacity_in(capacity, self.align, allocator.clone());
+        }
+
+        Self::with_capacity_aligned(capacity, self.align)
+    }
+
+    /// Grow the capacity to at least `new_capacity` with `Vec`'s reallocation, which may extend
+    /// the allocation in place instead of allocating new memory and copying.
+    ///
+    /// Returns `false` and does nothing if the memory is not allocated by `Vec<u8>`, or if the
+    /// reallocation fails.
+    ///
+    /// # Safety
+    ///
+    /// - Caller must ensure that data inside `..len` is initialized
+    /// - `&mut self` means that no one refers to the memory, so it can be moved
+    pub unsafe fn grow_in_place(&mut self, len: usize, new_capacity: usize) -> bool {
+        debug_assert!(len <= self.capacity);
+        #[cfg(feature = "allocator_api")]
+        if self.allocator.is_some() {
+            return false;
+        }
+
+        if self.align != 1 {
+            return false;
+        }
+
+        // SAFETY: the memory is allocated by `Vec<u8>` with `capacity`, and caller must ensure
+        // that data inside `..len` is initialized
+        let mut vec =
+            ManuallyDrop::new(unsafe { Vec::from_raw_parts(self.ptr, len, self.capacity) });
+        // It must not panic, otherwise both `vec` and `self` would free the memory on unwinding
+        if vec
+            .try_reserve_exact(new_capacity.saturating_sub(len))
+            .is_err()
+        {
+            return false;
+        }
+
+        self.ptr = vec.as_mut_ptr();
+        self.capacity = vec.capacity();
+        true
+    }
+
+    /// The data inside the range may be uninitialized.
+    ///
+    /// The range should not cover the region that the owner of the buffer may still write to.
+    #[inline(always)]
+    pub fn slice_uninit(&self, range: impl RangeBounds<usize>) -> &[MaybeUninit<u8>] {
+        let (start, end) = get_range(range, self.capacity);
+        // SAFETY: the range is inside the allocation, and `MaybeUninit<u8>` can be uninitialized
+        unsafe {
+            core::slice::from_raw_parts(self.ptr.add(start) as *const MaybeUninit<u8>, end - start)
+        }
+    }
+
     /// # Safety
     ///
     /// - Caller must ensure that data inside the range is initialized
     /// - Caller must ensure that there is no one can write to the target range when the slice is alive
     #[inline(always)]
     pub unsafe fn slice(&self, range: impl RangeBounds<usize>) -> &[u8] {
-        let (start, end) = get_range(range, self.capacity);
+        let data = self.slice_uninit(range);
+        #[cfg(debug_assertions)]
+        {
+            let end = data.as_ptr() as usize - self.ptr as usize + data.len();
+            let initialized = self.initialized.load(Ordering::Relaxed);
+            debug_assert!(
+                end <= initialized,
+                "reading uninitialized data: {} > {}",
+                end,
+                initialized
+            );
+        }
         // SAFETY: Caller must ensure that data inside the range is initialized
-        unsafe { std::slice::from_raw_parts(self.ptr.add(start), end - start) }
+        unsafe { &*(data as *const [MaybeUninit<u8>] as *const [u8]) }
+    }
+
+    /// Mark the data inside `..len` as initialized. It's only tracked in debug builds.
+    #[inline(always)]
+    pub fn mark_initialized(&self, len: usize) {
+        debug_assert!(len <= self.capacity);
+        #[cfg(debug_assertions)]
+        self.initialized.fetch_max(len, Ordering::Relaxed);
+        #[cfg(not(debug_assertions))]
+        let _ = len;
     }
 
     #[inline(always)]
@@ -44,6 +228,28 @@ impl RawBytes {
     pub fn ptr(&self) -> *mut u8 {
         self.ptr
     }
+
+    /// # Safety
+    ///
+    /// - Caller must ensure that data inside `..len` is initialized
+    #[inline(always)]
+    pub unsafe fn into_vec(self, len: usize) -> Vec<u8> {
+        debug_assert!(len <= self.capacity);
+        #[cfg(feature = "allocator_api")]
+        let copy = self.align != 1 || self.allocator.is_some();
+        #[cfg(not(feature = "allocator_api"))]
+        let copy = self.align != 1;
+        if copy {
+            // `Vec<u8>` cannot adopt memory with a different alignment or allocator
+            // SAFETY: Caller must ensure that data inside `..len` is initialized
+            return unsafe { self.slice(..len) }.to_vec();
+        }
+
+        let this = ManuallyDrop::new(self);
+        // SAFETY: `this` will not be dropped, so the ownership of the memory is moved into the vec.
+        // Caller must ensure that data inside `..len` is initialized
+        unsafe { Vec::from_raw_parts(this.ptr, len, this.capacity) }
+    }
 }
 
 impl From<Vec<u8>> for RawBytes {
@@ -52,6 +258,59 @@ impl From<Vec<u8>> for RawBytes {
         Self {
             ptr: vec.as_mut_ptr(),
             capacity: vec.capacity(),
+            align: 1,
+            #[cfg(feature = "allocator_api")]
+            allocator: None,
+            #[cfg(debug_assertions)]
+            initialized: AtomicUsize::new(vec.len()),
         }
     }
 }
+
+/// An `Arc<RawBytes>` aligned to at most 4 bytes, so that `BytesSlice` with `u16` offsets takes
+/// 12 bytes on 64-bit targets instead of being padded to 16.
+#[repr(C, packed(4))]
+pub(crate) struct PackedArc(Arc<RawBytes>);
+
+impl PackedArc {
+    /// A copy of the `Arc` that must not be dropped, so it doesn't touch the reference count.
+    #[inline(always)]
+    fn get(&self) -> ManuallyDrop<Arc<RawBytes>> {
+        // SAFETY: the field is valid for reads, and the copy is never dropped
+        ManuallyDrop::new(unsafe { core::ptr::read_unaligned(core::ptr::addr_of!(self.0)) })
+    }
+
+    #[inline(always)]
+    pub fn as_ptr(&self) -> *const RawBytes {
+        Arc::as_ptr(&self.get())
+    }
+
+    #[inline(always)]
+    pub fn strong_count(&self) -> usize {
+        Arc::strong_count(&self.get())
+    }
+}
+
+impl From<Arc<RawBytes>> for PackedArc {
+    #[inline(always)]
+    fn from(arc: Arc<RawBytes>) -> Self {
+        Self(arc)
+    }
+}
+
+impl Clone for PackedArc {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.get()))
+    }
+}
+
+impl Deref for PackedArc {
+    type Target = RawBytes;
+
+    #[inline(always)]
+    fn deref(&self) -> &RawBytes {
+        // SAFETY: `self` holds a strong reference, so the `RawBytes` lives as long as `self`
+        unsafe { &*self.as_ptr() }
+    }
+}
@@ -0,0 +1,248 @@
+use crate::BytesSlice;
+
+/// A cursor over a [`BytesSlice`] for incremental parsing.
+///
+/// All the read methods return `None` without consuming anything if there are not enough bytes.
+#[derive(Debug, Clone)]
+pub struct SliceReader {
+    rest: BytesSlice,
+}
+
+impl SliceReader {
+    #[inline(always)]
+    pub fn new(slice: BytesSlice) -> Self {
+        Self { rest: slice }
+    }
+
+    /// The number of bytes that haven't been read
+    #[inline(always)]
+    pub fn remaining(&self) -> usize {
+        self.rest.len()
+    }
+
+    /// The bytes that haven't been read
+    #[inline(always)]
+    pub fn rest(&self) -> &BytesSlice {
+        &self.rest
+    }
+
+    #[inline(always)]
+    pub fn into_inner(self) -> BytesSlice {
+        self.rest
+    }
+
+    /// Read `n` bytes as a zero-copy slice.
+    #[inline]
+    pub fn read_exact(&mut self, n: usize) -> Option<BytesSlice> {
+        if n > self.rest.len() {
+            return None;
+        }
+
+        let ans = self.rest.slice_clone(..n);
+        self.rest.slice_(n..);
+        Some(ans)
+    }
+
+    /// Read an unsigned LEB128 varint.
+    ///
+    /// Returns `None` if it's truncated or overflows `u64`.
+    #[inline]
+    pub fn read_uvarint(&mut self) -> Option<u64> {
+        let mut value: u64 = 0;
+        for (i, &byte) in self.rest.as_bytes().iter().enumerate().take(10) {
+            let bits = (byte & 0x7f) as u64;
+            if i == 9 && bits > 1 {
+                return None;
+            }
+
+            value |= bits << (7 * i);
+            if byte & 0x80 == 0 {
+                self.rest.slice_(i + 1..);
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    /// Read a zigzag encoded LEB128 varint.
+    #[inline]
+    pub fn read_ivarint(&mut self) -> Option<i64> {
+        let value = self.read_uvarint()?;
+        Some(((value >> 1) as i64) ^ -((value & 1) as i64))
+    }
+
+    #[inline]
+    fn read_array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let ans: [u8; N] = self.rest.as_bytes().get(..N)?.try_into().unwrap();
+        self.rest.slice_(N..);
+        Some(ans)
+    }
+
+    #[inline]
+    pub fn read_u8(&mut self) -> Option<u8> {
+        self.read_array::<1>().map(|x| x[0])
+    }
+}
+
+macro_rules! impl_read_num {
+    ($($ty:ty => $le:ident, $be:ident;)*) => {
+        impl SliceReader {
+            $(
+                #[inline]
+                pub fn $le(&mut self) -> Option<$ty> {
+                    self.read_array().map(<$ty>::from_le_bytes)
+                }
+
+                #[inline]
+                pub fn $be(&mut self) -> Option<$ty> {
+                    self.read_array().map(<$ty>::from_be_bytes)
+                }
+            )*
+        }
+    };
+}
+
+impl_read_num! {
+    u16 => read_u16_le, read_u16_be;
+    u32 => read_u32_le, read_u32_be;
+    u64 => read_u64_le, read_u64_be;
+    i16 => read_i16_le, read_i16_be;
+    i32 => read_i32_le, read_i32_be;
+    i64 => read_i64_le, read_i64_be;
+    f32 => read_f32_le, read_f32_be;
+    f64 => read_f64_le, read_f64_be;
+}
+
+#[cfg(test)]
+mod test_reader {
+    use super::*;
+
+    #[test]
+    fn read() {
+        let a = BytesSlice::from_bytes(&[1, 0x34, 0x12, 0x12, 0x34, 0x56, 0x78, 9, 10, 11]);
+        let mut r = SliceReader::new(a.clone());
+        assert_eq!(r.read_u8(), Some(1));
+        assert_eq!(r.read_u16_le(), Some(0x1234));
+        assert_eq!(r.read_u32_be(), Some(0x12345678));
+        assert_eq!(r.remaining(), 3);
+        assert_eq!(r.read_u32_le(), None);
+        assert_eq!(r.remaining(), 3);
+        let b = r.read_exact(2).unwrap();
+        assert_eq!(b.as_bytes(), &[9, 10]);
+        assert!(b.ptr_eq(&a));
+        assert_eq!(r.read_exact(2), None);
+        assert_eq!(r.read_u16_be(), None);
+        assert_eq!(r.read_u8(), Some(11));
+        assert_eq!(r.remaining(), 0);
+        assert_eq!(r.read_u8(), None);
+    }
+
+    #[test]
+    fn push_and_read_num() {
+        use crate::AppendOnlyBytes;
+        let mut a = AppendOnlyBytes::new();
+        a.push_u16_le(0x1234);
+        a.push_u16_be(0x1234);
+        a.push_u32_le(0x12345678);
+        a.push_u32_be(0x12345678);
+        a.push_u64_le(u64::MAX - 1);
+        a.push_u64_be(u64::MAX - 1);
+        a.push_i16_le(-2);
+        a.push_i16_be(-2);
+        a.push_i32_le(i32::MIN);
+        a.push_i32_be(i32::MIN);
+        a.push_i64_le(-3);
+        a.push_i64_be(-3);
+        a.push_f32_le(1.5);
+        a.push_f32_be(-1.5);
+        a.push_f64_le(f64::MAX);
+        a.push_f64_be(f64::MIN_POSITIVE);
+        assert_eq!(&a[..4], &[0x34, 0x12, 0x12, 0x34]);
+
+        let mut r = SliceReader::new(a.to_slice());
+        assert_eq!(r.read_u16_le(), Some(0x1234));
+        assert_eq!(r.read_u16_be(), Some(0x1234));
+        assert_eq!(r.read_u32_le(), Some(0x12345678));
+        assert_eq!(r.read_u32_be(), Some(0x12345678));
+        assert_eq!(r.read_u64_le(), Some(u64::MAX - 1));
+        assert_eq!(r.read_u64_be(), Some(u64::MAX - 1));
+        assert_eq!(r.read_i16_le(), Some(-2));
+        assert_eq!(r.read_i16_be(), Some(-2));
+        assert_eq!(r.read_i32_le(), Some(i32::MIN));
+        assert_eq!(r.read_i32_be(), Some(i32::MIN));
+        assert_eq!(r.read_i64_le(), Some(-3));
+        assert_eq!(r.read_i64_be(), Some(-3));
+        assert_eq!(r.read_f32_le(), Some(1.5));
+        assert_eq!(r.read_f32_be(), Some(-1.5));
+        assert_eq!(r.read_f64_le(), Some(f64::MAX));
+        assert_eq!(r.read_f64_be(), Some(f64::MIN_POSITIVE));
+        assert_eq!(r.remaining(), 0);
+    }
+
+    #[test]
+    fn varint() {
+        use crate::AppendOnlyBytes;
+        fn encode_u(v: u64) -> AppendOnlyBytes {
+            let mut a = AppendOnlyBytes::new();
+            a.push_uvarint(v);
+            a
+        }
+
+        assert_eq!(encode_u(0).as_bytes(), &[0]);
+        assert_eq!(encode_u(127).as_bytes(), &[0x7f]);
+        assert_eq!(encode_u(128).as_bytes(), &[0x80, 0x01]);
+        assert_eq!(encode_u(300).as_bytes(), &[0xac, 0x02]);
+        assert_eq!(
+            encode_u(u64::MAX).as_bytes(),
+            &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]
+        );
+
+        let mut a = AppendOnlyBytes::new();
+        a.push_ivarint(0);
+        a.push_ivarint(-1);
+        a.push_ivarint(1);
+        a.push_ivarint(-64);
+        assert_eq!(a.as_bytes(), &[0, 1, 2, 0x7f]);
+
+        let values = [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX];
+        let ivalues = [0, -1, 1, 63, -64, 64, i64::MIN, i64::MAX];
+        let mut a = AppendOnlyBytes::new();
+        for &v in values.iter() {
+            a.push_uvarint(v);
+        }
+        for &v in ivalues.iter() {
+            a.push_ivarint(v);
+        }
+        let mut r = SliceReader::new(a.to_slice());
+        for &v in values.iter() {
+            assert_eq!(r.read_uvarint(), Some(v));
+        }
+        for &v in ivalues.iter() {
+            assert_eq!(r.read_ivarint(), Some(v));
+        }
+        assert_eq!(r.read_uvarint(), None);
+
+        // truncated, without a terminating byte
+        let mut r = SliceReader::new(BytesSlice::from_bytes(&[0x80, 0x80]));
+        assert_eq!(r.read_uvarint(), None);
+        assert_eq!(r.remaining(), 2);
+        let mut r = SliceReader::new(BytesSlice::from_bytes(&[0xff; 9]));
+        assert_eq!(r.read_uvarint(), None);
+        assert_eq!(r.remaining(), 9);
+        // terminated, but exceeds u64
+        let mut bytes = [0xff; 10];
+        bytes[9] = 0x01;
+        let mut r = SliceReader::new(BytesSlice::from_bytes(&bytes));
+        assert_eq!(r.read_uvarint(), Some(u64::MAX));
+        bytes[9] = 0x02;
+        let mut r = SliceReader::new(BytesSlice::from_bytes(&bytes));
+        assert_eq!(r.read_uvarint(), None);
+        assert_eq!(r.remaining(), 10);
+        // terminated after the 10th byte
+        let mut r = SliceReader::new(BytesSlice::from_bytes(&[
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x81, 0x00,
+        ]));
+        assert_eq!(r.read_uvarint(), None);
+        assert_eq!(r.remaining(), 11);
+    }
+}
@@ -0,0 +1,44 @@
+use crate::BytesSlice;
+use rkyv::{
+    rancor::Fallible,
+    ser::{Allocator, Writer},
+    vec::{ArchivedVec, VecResolver},
+    Archive, Deserialize, Place, Serialize,
+};
+
+impl Archive for BytesSlice {
+    type Archived = ArchivedVec<u8>;
+    type Resolver = VecResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        ArchivedVec::resolve_from_len(self.len(), resolver, out);
+    }
+}
+
+impl<S: Fallible + Allocator + Writer + ?Sized> Serialize<S> for BytesSlice {
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        ArchivedVec::serialize_from_slice(self.as_bytes(), serializer)
+    }
+}
+
+impl<D: Fallible + ?Sized> Deserialize<BytesSlice, D> for ArchivedVec<u8> {
+    fn deserialize(&self, _: &mut D) -> Result<BytesSlice, D::Error> {
+        Ok(BytesSlice::from_bytes(self.as_slice()))
+    }
+}
+
+#[cfg(test)]
+mod test_rkyv {
+    use super::*;
+    use rkyv::rancor::Error;
+
+    #[test]
+    fn archive() {
+        let a = BytesSlice::from_bytes(&[1, 2, 3, 4]).slice_clone(1..);
+        let bytes = rkyv::to_bytes::<Error>(&a).unwrap();
+        let archived = rkyv::access::<ArchivedVec<u8>, Error>(&bytes).unwrap();
+        assert_eq!(archived.as_slice(), &[2, 3, 4]);
+        let b: BytesSlice = rkyv::deserialize::<BytesSlice, Error>(archived).unwrap();
+        assert_eq!(a, b);
+    }
+}
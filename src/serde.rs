@@ -1,6 +1,9 @@
-use crate::BytesSlice;
+//! `serde` support for [`AppendOnlyBytes`] and [`BytesSlice`].
+
+use crate::{AppendOnlyBytes, BytesSlice};
+use alloc::vec::Vec;
 use serde::{
-    de::{SeqAccess, Visitor},
+    de::{DeserializeSeed, SeqAccess, Visitor},
     Deserialize, Serialize,
 };
 
@@ -9,20 +12,45 @@ impl Serialize for BytesSlice {
     where
         S: serde::Serializer,
     {
-        serializer.serialize_bytes(self.bytes())
+        serialize_bytes(self.bytes(), serializer)
+    }
+}
+
+impl Serialize for AppendOnlyBytes {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serialize_bytes(self.as_bytes(), serializer)
     }
 }
 
+fn serialize_bytes<S>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.serialize_bytes(bytes)
+}
+
 impl<'de> Deserialize<'de> for BytesSlice {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        AppendOnlyBytes::deserialize(deserializer).map(AppendOnlyBytes::to_slice)
+    }
+}
+
+impl<'de> Deserialize<'de> for AppendOnlyBytes {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
         struct BytesVisitor;
         impl<'de> Visitor<'de> for BytesVisitor {
-            type Value = BytesSlice;
+            type Value = AppendOnlyBytes;
 
-            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
                 formatter.write_str("BytesSliceVisitor deserialize failed")
             }
 
@@ -30,14 +58,14 @@ impl<'de> Deserialize<'de> for BytesSlice {
             where
                 E: serde::de::Error,
             {
-                Ok(BytesSlice::from_bytes(v))
+                Ok(AppendOnlyBytes::from_slices(&[v]))
             }
 
             fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
             where
                 E: serde::de::Error,
             {
-                Ok(BytesSlice::from_bytes(v))
+                Ok(AppendOnlyBytes::from_slices(&[v]))
             }
 
             fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
@@ -47,14 +75,218 @@ impl<'de> Deserialize<'de> for BytesSlice {
                 let bytes: Vec<u8> = serde::de::Deserialize::deserialize(
                     serde::de::value::SeqAccessDeserializer::new(seq),
                 )?;
+                Ok(AppendOnlyBytes::from_vec(bytes))
+            }
+        }
+
+        deserialize_bytes(deserializer, BytesVisitor)
+    }
+}
+
+fn deserialize_bytes<'de, D, V>(deserializer: D, visitor: V) -> Result<V::Value, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    V: Visitor<'de>,
+{
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_seq(visitor)
+    } else {
+        deserializer.deserialize_bytes(visitor)
+    }
+}
+
+/// Deserialize a [`BytesSlice`] by appending the bytes to a shared [`AppendOnlyBytes`].
+///
+/// Deserializing many slices with the same buffer saves an allocation per slice. The slices
+/// deserialized without reallocation in between share the same allocation.
+#[derive(Debug)]
+pub struct SliceSeed<'a>(pub &'a mut AppendOnlyBytes);
+
+impl<'de> DeserializeSeed<'de> for SliceSeed<'_> {
+    type Value = BytesSlice;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct AppendVisitor<'a>(&'a mut AppendOnlyBytes);
+        impl<'de> Visitor<'de> for AppendVisitor<'_> {
+            type Value = BytesSlice;
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                formatter.write_str("bytes")
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(self.0.push_slice_ret(v))
+            }
+
+            fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                // Collect first, so that nothing is appended if it fails halfway
+                let bytes: Vec<u8> = serde::de::Deserialize::deserialize(
+                    serde::de::value::SeqAccessDeserializer::new(seq),
+                )?;
+                Ok(self.0.push_slice_ret(&bytes))
+            }
+        }
+
+        deserialize_bytes(deserializer, AppendVisitor(self.0))
+    }
+}
+
+/// Deserialize a sequence of [`BytesSlice`]s with [`SliceSeed`], so they share one buffer.
+#[derive(Debug)]
+pub struct SliceVecSeed<'a>(pub &'a mut AppendOnlyBytes);
+
+impl<'de> DeserializeSeed<'de> for SliceVecSeed<'_> {
+    type Value = Vec<BytesSlice>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct SeqVisitor<'a>(&'a mut AppendOnlyBytes);
+        impl<'de> Visitor<'de> for SeqVisitor<'_> {
+            type Value = Vec<BytesSlice>;
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                formatter.write_str("a sequence of bytes")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut slices = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+                while let Some(slice) = seq.next_element_seed(SliceSeed(self.0))? {
+                    slices.push(slice);
+                }
+                Ok(slices)
+            }
+        }
+
+        deserializer.deserialize_seq(SeqVisitor(self.0))
+    }
+}
+
+/// Serialize a [`BytesSlice`] as a lowercase hex string.
+///
+/// Use it with `#[serde(with = "append_only_bytes::serde::hex")]`.
+pub mod hex {
+    use crate::BytesSlice;
+    use alloc::{string::String, vec::Vec};
+    use serde::{de::Visitor, Deserializer, Serializer};
+
+    const CHARS: &[u8; 16] = b"0123456789abcdef";
+
+    pub fn serialize<S>(bytes: &BytesSlice, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut s = String::with_capacity(bytes.len() * 2);
+        for &b in bytes.as_bytes() {
+            s.push(CHARS[(b >> 4) as usize] as char);
+            s.push(CHARS[(b & 0xf) as usize] as char);
+        }
+        serializer.serialize_str(&s)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<BytesSlice, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct HexVisitor;
+        impl<'de> Visitor<'de> for HexVisitor {
+            type Value = BytesSlice;
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                formatter.write_str("a hex string")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                if v.len() % 2 != 0 {
+                    return Err(E::custom("odd length hex string"));
+                }
+
+                let v = v.as_bytes();
+                let mut bytes = Vec::with_capacity(v.len() / 2);
+                for pair in v.chunks_exact(2) {
+                    match (hex_value(pair[0]), hex_value(pair[1])) {
+                        (Some(hi), Some(lo)) => bytes.push((hi << 4) | lo),
+                        _ => return Err(E::custom("invalid hex character")),
+                    }
+                }
                 Ok(BytesSlice::from_bytes(&bytes))
             }
         }
 
+        deserializer.deserialize_str(HexVisitor)
+    }
+
+    fn hex_value(c: u8) -> Option<u8> {
+        match c {
+            b'0'..=b'9' => Some(c - b'0'),
+            b'a'..=b'f' => Some(c - b'a' + 10),
+            b'A'..=b'F' => Some(c - b'A' + 10),
+            _ => None,
+        }
+    }
+}
+
+/// Serialize a [`BytesSlice`] as a base64 string in human-readable formats. Binary formats keep
+/// the raw bytes.
+///
+/// Use it with `#[serde(with = "append_only_bytes::serde::base64")]`.
+#[cfg(feature = "serde-base64")]
+pub mod base64 {
+    use crate::BytesSlice;
+    use ::base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{de::Visitor, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S>(bytes: &BytesSlice, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&STANDARD.encode(bytes.as_bytes()))
+        } else {
+            bytes.serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<BytesSlice, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct Base64Visitor;
+        impl<'de> Visitor<'de> for Base64Visitor {
+            type Value = BytesSlice;
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                formatter.write_str("a base64 string")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                STANDARD.decode(v).map(BytesSlice::from).map_err(E::custom)
+            }
+        }
+
         if deserializer.is_human_readable() {
-            deserializer.deserialize_seq(BytesVisitor)
+            deserializer.deserialize_str(Base64Visitor)
         } else {
-            deserializer.deserialize_bytes(BytesVisitor)
+            BytesSlice::deserialize(deserializer)
         }
     }
 }
@@ -63,6 +295,7 @@ impl<'de> Deserialize<'de> for BytesSlice {
 mod test_serde {
     use super::*;
     use std::collections::HashMap;
+    use std::prelude::rust_2021::*;
 
     #[test]
     fn test() {
@@ -78,4 +311,92 @@ mod test_serde {
         let b: HashMap<i32, BytesSlice> = postcard::from_bytes(&s).unwrap();
         assert_eq!(a, b);
     }
+
+    #[test]
+    fn append_only_bytes() {
+        let mut a = AppendOnlyBytes::new();
+        a.push_slice(&[1, 2, 3]);
+        let s = serde_json::to_string(&a).unwrap();
+        assert_eq!(s, "[1,2,3]");
+        let b: AppendOnlyBytes = serde_json::from_str(&s).unwrap();
+        assert_eq!(a, b);
+        let s = postcard::to_allocvec(&a).unwrap();
+        let b: AppendOnlyBytes = postcard::from_bytes(&s).unwrap();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    #[cfg(feature = "serde-base64")]
+    fn base64() {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Data {
+            #[serde(with = "crate::serde::base64")]
+            data: BytesSlice,
+        }
+
+        let a = Data {
+            data: BytesSlice::from_bytes(b"hello"),
+        };
+        let s = serde_json::to_string(&a).unwrap();
+        assert_eq!(s, r#"{"data":"aGVsbG8="}"#);
+        let b: Data = serde_json::from_str(&s).unwrap();
+        assert_eq!(a, b);
+        assert!(serde_json::from_str::<Data>(r#"{"data":"!!"}"#).is_err());
+        // binary formats are not affected
+        let s = postcard::to_allocvec(&a).unwrap();
+        assert_eq!(&s[1..], b"hello");
+        let b: Data = postcard::from_bytes(&s).unwrap();
+        assert_eq!(a, b);
+        // the fields without the attribute keep the array format
+        assert_eq!(
+            serde_json::to_string(&a.data).unwrap(),
+            "[104,101,108,108,111]"
+        );
+        let b: BytesSlice = serde_json::from_str("[1,2,3]").unwrap();
+        assert_eq!(b.as_bytes(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn hex() {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Data {
+            #[serde(with = "crate::serde::hex")]
+            data: BytesSlice,
+        }
+
+        let a = Data {
+            data: BytesSlice::from_bytes(&[0x01, 0xab, 0xff]),
+        };
+        let s = serde_json::to_string(&a).unwrap();
+        assert_eq!(s, r#"{"data":"01abff"}"#);
+        let b: Data = serde_json::from_str(&s).unwrap();
+        assert_eq!(a, b);
+        assert!(serde_json::from_str::<Data>(r#"{"data":"01a"}"#).is_err());
+        assert!(serde_json::from_str::<Data>(r#"{"data":"0g"}"#).is_err());
+    }
+
+    #[test]
+    fn slice_vec_seed() {
+        let a = vec![
+            BytesSlice::from_bytes(b"12"),
+            BytesSlice::from_bytes(b""),
+            BytesSlice::from_bytes(b"345"),
+        ];
+        let s = postcard::to_allocvec(&a).unwrap();
+        let mut buf = AppendOnlyBytes::with_capacity(64);
+        let b = SliceVecSeed(&mut buf)
+            .deserialize(&mut postcard::Deserializer::from_bytes(&s))
+            .unwrap();
+        assert_eq!(a, b);
+        assert!(b.iter().all(|x| x.ptr_eq(&b[0])));
+        assert_eq!(buf.as_bytes(), b"12345");
+
+        let s = serde_json::to_string(&a).unwrap();
+        let b = SliceVecSeed(&mut buf)
+            .deserialize(&mut serde_json::Deserializer::from_str(&s))
+            .unwrap();
+        assert_eq!(a, b);
+        assert!(buf.owns(&b[2]));
+        assert_eq!(buf.as_bytes(), b"1234512345");
+    }
 }
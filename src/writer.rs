@@ -0,0 +1,178 @@
+use alloc::vec::Vec;
+
+use crate::{AppendOnlyBytes, BytesSlice};
+
+/// Appends to an [`AppendOnlyBytes`] and records each write as a separate [`BytesSlice`] segment.
+///
+/// The segments written without reallocation in between can be merged by
+/// [`BytesSlice::try_merge`].
+#[derive(Debug, Default)]
+pub struct SegmentedWriter {
+    bytes: AppendOnlyBytes,
+    segments: Vec<BytesSlice>,
+}
+
+impl SegmentedWriter {
+    #[inline]
+    pub fn new(bytes: AppendOnlyBytes) -> Self {
+        Self {
+            bytes,
+            segments: Vec::new(),
+        }
+    }
+
+    /// Append `slice` as a new segment. Empty slices are not recorded.
+    #[inline]
+    pub fn push_slice(&mut self, slice: &[u8]) {
+        if slice.is_empty() {
+            return;
+        }
+
+        let segment = self.bytes.push_slice_ret(slice);
+        self.segments.push(segment);
+    }
+
+    #[inline(always)]
+    pub fn segments(&self) -> &[BytesSlice] {
+        &self.segments
+    }
+
+    #[inline(always)]
+    pub fn bytes(&self) -> &AppendOnlyBytes {
+        &self.bytes
+    }
+
+    #[inline(always)]
+    pub fn into_segments(self) -> Vec<BytesSlice> {
+        self.segments
+    }
+
+    #[inline(always)]
+    pub fn into_inner(self) -> (AppendOnlyBytes, Vec<BytesSlice>) {
+        (self.bytes, self.segments)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::io::Write for SegmentedWriter {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.push_slice(buf);
+        Ok(buf.len())
+    }
+
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Appends to an [`AppendOnlyBytes`] and keeps the written bytes as a list of [`BytesSlice`]
+/// segments, like the leaves of a rope.
+///
+/// Unlike [`SegmentedWriter`], a write extends the last segment when it's adjacent to it, so a
+/// new segment only starts after a reallocation.
+#[derive(Debug, Default)]
+pub struct RopeBuilder {
+    bytes: AppendOnlyBytes,
+    segments: Vec<BytesSlice>,
+}
+
+impl RopeBuilder {
+    #[inline]
+    pub fn new(bytes: AppendOnlyBytes) -> Self {
+        Self {
+            bytes,
+            segments: Vec::new(),
+        }
+    }
+
+    /// Append `data` and return the segment that contains it.
+    #[inline]
+    pub fn push(&mut self, data: &[u8]) -> &BytesSlice {
+        let new = self.bytes.push_slice_ret(data);
+        let merged = match self.segments.last_mut() {
+            Some(last) => last.try_merge(&new).is_ok(),
+            None => false,
+        };
+        if !merged {
+            self.segments.push(new);
+        }
+
+        self.segments.last().unwrap()
+    }
+
+    #[inline(always)]
+    pub fn segments(&self) -> &[BytesSlice] {
+        &self.segments
+    }
+
+    #[inline(always)]
+    pub fn bytes(&self) -> &AppendOnlyBytes {
+        &self.bytes
+    }
+
+    #[inline(always)]
+    pub fn into_segments(self) -> Vec<BytesSlice> {
+        self.segments
+    }
+
+    #[inline(always)]
+    pub fn into_inner(self) -> (AppendOnlyBytes, Vec<BytesSlice>) {
+        (self.bytes, self.segments)
+    }
+}
+
+#[cfg(test)]
+mod test_writer {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn segments() {
+        use std::io::Write;
+        let mut w = SegmentedWriter::new(AppendOnlyBytes::with_capacity(16));
+        w.write_all(b"ab").unwrap();
+        w.write_all(b"").unwrap();
+        w.write_all(b"cde").unwrap();
+        write!(w, "{}", 6).unwrap();
+        let segments = w.into_segments();
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[0].as_bytes(), b"ab");
+        assert_eq!(segments[1].as_bytes(), b"cde");
+        assert_eq!(segments[2].as_bytes(), b"6");
+        let mut merged = segments[0].clone();
+        merged.try_merge(&segments[1]).unwrap();
+        merged.try_merge(&segments[2]).unwrap();
+        assert_eq!(merged.as_bytes(), b"abcde6");
+    }
+
+    #[test]
+    fn segments_realloc() {
+        let mut bytes = AppendOnlyBytes::new();
+        bytes.reserve_exact(2);
+        let mut w = SegmentedWriter::new(bytes);
+        w.push_slice(b"ab");
+        w.push_slice(b"cd");
+        let (bytes, segments) = w.into_inner();
+        assert_eq!(bytes.as_bytes(), b"abcd");
+        assert!(!segments[0].can_merge(&segments[1]));
+    }
+
+    #[test]
+    fn rope_builder() {
+        let mut r = RopeBuilder::new(AppendOnlyBytes::with_capacity(16));
+        for _ in 0..8 {
+            r.push(b"ab");
+        }
+        assert_eq!(r.segments().len(), 1);
+        assert_eq!(r.segments()[0].as_bytes(), b"abababababababab");
+
+        // reallocate
+        assert_eq!(r.push(b"cd").as_bytes(), b"cd");
+        assert_eq!(r.push(b"e").as_bytes(), b"cde");
+        let (bytes, segments) = r.into_inner();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(bytes.len(), 19);
+    }
+}